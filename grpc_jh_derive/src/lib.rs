@@ -0,0 +1,141 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use sha2::{Digest, Sha256};
+use syn::{
+    DeriveInput, Error, Expr, ExprArray, ExprLit, Lit, LitStr, Result, parse_macro_input,
+    spanned::Spanned,
+};
+
+// #[derive(SolanaEvent)]
+// #[event(name = "pump_create", program = "6EF8...", discriminator = [27, 114, ...])]
+//
+// name 默认是类型名的 snake_case，discriminator 默认是 sha256("event:<类型名>") 的前 8 字节。
+// 除了 EventTrait 的实现，还会把事件注册到 decode::registered_events()，
+// 并把 discriminator 注册到 crate 根的 grpc_jh::solana_events!() 中，
+// 同一个 crate 中两个事件的 discriminator 相同时编译报 impl 冲突
+#[proc_macro_derive(SolanaEvent, attributes(event))]
+pub fn derive_solana_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct EventArgs {
+    name: Option<LitStr>,
+    program: Option<LitStr>,
+    discriminator: Option<[u8; 8]>,
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "SolanaEvent cannot be derived for generic types",
+        ));
+    }
+
+    let mut args = EventArgs::default();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("event"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                args.name = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("program") {
+                let program: LitStr = meta.value()?.parse()?;
+                let decoded = bs58::decode(program.value()).into_vec();
+                if !matches!(decoded, Ok(bytes) if bytes.len() == 32) {
+                    return Err(Error::new(program.span(), "program is not a valid pubkey"));
+                }
+                args.program = Some(program);
+            } else if meta.path.is_ident("discriminator") {
+                args.discriminator = Some(parse_discriminator(&meta.value()?.parse()?)?);
+            } else {
+                return Err(meta.error("expected name, program or discriminator"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let name = args
+        .name
+        .unwrap_or_else(|| LitStr::new(&to_snake_case(&ident.to_string()), Span::call_site()));
+    let program = match args.program {
+        Some(program) => quote!(Some(#program)),
+        None => quote!(None),
+    };
+    let discriminator = args.discriminator.unwrap_or_else(|| {
+        let digest = Sha256::digest(format!("event:{}", ident).as_bytes());
+        let mut discriminator = [0u8; 8];
+        discriminator.copy_from_slice(&digest[..8]);
+        discriminator
+    });
+    let key = u64::from_le_bytes(discriminator);
+
+    Ok(quote! {
+        impl ::grpc_jh::model::EventTrait for #ident {
+            const NAME: &'static str = #name;
+            const PROGRAM: Option<&'static str> = #program;
+            const DISCRIMINATOR: [u8; 8] = [#(#discriminator),*];
+
+            fn from_bytes(bytes: &[u8]) -> Result<Self, ::grpc_jh::error::ClientError> {
+                <Self as ::grpc_jh::model::__private::BorshDeserialize>::try_from_slice(bytes)
+                    .map_err(|e| ::grpc_jh::error::ClientError::Decode(e.to_string()))
+            }
+        }
+
+        impl crate::__solana_events::Registered for crate::__solana_events::Discriminator<#key> {
+            type Event = #ident;
+        }
+
+        ::grpc_jh::model::__private::inventory::submit! {
+            ::grpc_jh::decode::RegisteredEvent::new::<#ident>()
+        }
+    })
+}
+
+fn parse_discriminator(expr: &Expr) -> Result<[u8; 8]> {
+    let Expr::Array(ExprArray { elems, .. }) = expr else {
+        return Err(Error::new(
+            expr.span(),
+            "discriminator must be an array of 8 bytes",
+        ));
+    };
+    if elems.len() != 8 {
+        return Err(Error::new(
+            expr.span(),
+            "discriminator must be an array of 8 bytes",
+        ));
+    }
+    let mut discriminator = [0u8; 8];
+    for (byte, elem) in discriminator.iter_mut().zip(elems) {
+        *byte = match elem {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(int), ..
+            }) => int.base10_parse()?,
+            _ => return Err(Error::new(elem.span(), "expected a byte literal")),
+        };
+    }
+    Ok(discriminator)
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
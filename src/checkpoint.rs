@@ -0,0 +1,189 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use crate::error::ClientError;
+
+const DEFAULT_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+// 多个订阅共用同一个文件时，读改写需要串行
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Debug)]
+pub struct CheckpointConfig {
+    // JSON 文件，按订阅名称保存最后处理完的 slot
+    pub path: PathBuf,
+    pub name: String,
+    pub save_interval: Duration,
+}
+
+impl CheckpointConfig {
+    pub fn new(path: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            name: name.into(),
+            save_interval: DEFAULT_SAVE_INTERVAL,
+        }
+    }
+}
+
+pub fn load_checkpoints(path: &Path) -> Result<BTreeMap<String, u64>, ClientError> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+            ClientError::Config(format!("invalid checkpoint file {}: {}", path.display(), e))
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(ClientError::Config(format!(
+            "failed to read checkpoint file {}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+// 先写临时文件再 rename，进程中途退出也不会留下半个文件
+fn save_checkpoint(path: &Path, name: &str, slot: u64) -> Result<(), ClientError> {
+    let _guard = SAVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut checkpoints = load_checkpoints(path)?;
+    checkpoints.insert(name.to_string(), slot);
+
+    let write_error = |e: std::io::Error| {
+        ClientError::Config(format!(
+            "failed to write checkpoint file {}: {}",
+            path.display(),
+            e
+        ))
+    };
+    let tmp = path.with_extension("tmp");
+    let json =
+        serde_json::to_vec_pretty(&checkpoints).map_err(|e| ClientError::Config(e.to_string()))?;
+    fs::write(&tmp, json).map_err(write_error)?;
+    fs::rename(&tmp, path).map_err(write_error)
+}
+
+#[derive(Debug)]
+struct CheckpointState {
+    slot: Option<u64>,
+    saved: Option<u64>,
+    last_save: Instant,
+    // 后台正在写文件，避免写入堆积
+    saving: bool,
+}
+
+// 记录已经处理完的最大 slot，按 save_interval 写入文件，退出时再 flush 一次
+#[derive(Clone, Debug)]
+pub struct Checkpointer {
+    config: Arc<CheckpointConfig>,
+    state: Arc<Mutex<CheckpointState>>,
+    // 写文件时持有，保证后读到的 slot 后写入
+    writer: Arc<Mutex<()>>,
+}
+
+impl Checkpointer {
+    pub fn new(config: CheckpointConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(CheckpointState {
+                slot: None,
+                saved: None,
+                last_save: Instant::now(),
+                saving: false,
+            })),
+            writer: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    // 上次退出时保存的 slot
+    pub fn load(&self) -> Result<Option<u64>, ClientError> {
+        Ok(load_checkpoints(&self.config.path)?
+            .get(&self.config.name)
+            .copied())
+    }
+
+    pub fn slot(&self) -> Option<u64> {
+        self.state.lock().unwrap().slot
+    }
+
+    // 在 tokio 运行时中时，文件在阻塞线程中写入，不阻塞调用方
+    pub fn record(&self, slot: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.slot = Some(state.slot.map_or(slot, |last| last.max(slot)));
+        if state.saving || state.last_save.elapsed() < self.config.save_interval {
+            return;
+        }
+        state.saving = true;
+        state.last_save = Instant::now();
+        drop(state);
+
+        let checkpoint = self.clone();
+        let save = move || {
+            if let Err(e) = checkpoint.save() {
+                warn!(
+                    "Failed to save checkpoint {}: {}",
+                    checkpoint.config.name, e
+                );
+            }
+            checkpoint.state.lock().unwrap().saving = false;
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(save);
+            }
+            Err(_) => save(),
+        }
+    }
+
+    pub fn flush(&self) -> Result<(), ClientError> {
+        self.save()
+    }
+
+    // 写文件时不持有 state 的锁，record 不会被阻塞
+    fn save(&self) -> Result<(), ClientError> {
+        let _writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let slot = {
+            let mut state = self.state.lock().unwrap();
+            state.last_save = Instant::now();
+            match state.slot {
+                Some(slot) if state.saved != Some(slot) => slot,
+                _ => return Ok(()),
+            }
+        };
+        save_checkpoint(&self.config.path, &self.config.name, slot)?;
+        self.state.lock().unwrap().saved = Some(slot);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoints_per_subscription() {
+        let path =
+            std::env::temp_dir().join(format!("grpc_jh_checkpoint_{}.json", std::process::id()));
+        let pump = Checkpointer::new(CheckpointConfig::new(&path, "pump"));
+        let amm = Checkpointer::new(CheckpointConfig::new(&path, "amm"));
+        assert_eq!(pump.load().unwrap(), None);
+
+        pump.record(100);
+        pump.record(99);
+        amm.record(7);
+        pump.flush().unwrap();
+        amm.flush().unwrap();
+
+        assert_eq!(pump.load().unwrap(), Some(100));
+        assert_eq!(amm.load().unwrap(), Some(7));
+        fs::remove_file(&path).unwrap();
+    }
+}
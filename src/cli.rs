@@ -0,0 +1,416 @@
+use std::{io::Write, path::PathBuf};
+
+use yellowstone_grpc_proto::geyser::{CommitmentLevel, SubscribeRequest};
+
+use crate::{
+    error::ClientError,
+    handle::{Decoder, EventHandler, PUMP_AMM_PROGRAM_ID, PUMP_PROGRAM_ID},
+    output::{EventPrinter, OutputFormat},
+    request::{AccountsFilter, SubscribeRequestBuilder, TransactionsFilter},
+    router::FilterRouter,
+    subscription::{PROGRAMS_FILTER, WALLETS_FILTER},
+};
+
+// --program 中的 Pump / Pump AMM 单独放在各自的过滤器，watch 按过滤器名称分发给对应的解析器
+pub const PUMP_FILTER: &str = "pump";
+pub const PUMP_AMM_FILTER: &str = "pump_amm";
+pub const MINTS_FILTER: &str = "mints";
+pub const ACCOUNTS_FILTER: &str = "accounts";
+
+pub const USAGE: &str = "\
+usage: grpc_jh <command> [options]
+
+commands:
+  watch     --program <id>... --wallet <id>... --mint <id>...
+            [--commitment processed|confirmed|finalized] [--format pretty|json|csv]
+            [--checkpoint <file>]
+  decode    <base64 | log line | JSON log array>... | --file <file>
+            [--idl <anchor idl.json>...] [--format pretty|json|csv]
+  record    --output <file> --program <id>... --wallet <id>... --mint <id>...
+            [--commitment ...]
+  replay    <file> [--format pretty|json|csv]
+  accounts  --account <pubkey>... --owner <pubkey>... [--commitment ...] [--format ...]
+  run       --config <file>
+
+endpoints and x-token are read from YELLOWSTONE_GRPC_URL / YELLOWSTONE_X_TOKEN;
+run reads them from the config file, any key can be overridden with GRPC_JH__<SECTION>__<KEY>";
+
+// watch / record 共用的交易过滤条件，每类地址对应一个命名过滤器
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionArgs {
+    pub programs: Vec<String>,
+    pub wallets: Vec<String>,
+    pub mints: Vec<String>,
+    pub commitment: CommitmentLevel,
+}
+
+impl TransactionArgs {
+    pub fn request(&self) -> Result<SubscribeRequest, ClientError> {
+        let mut builder = SubscribeRequestBuilder::new().commitment(self.commitment);
+        let (pump, programs): (Vec<_>, Vec<_>) = self
+            .programs
+            .iter()
+            .cloned()
+            .partition(|program| program == PUMP_PROGRAM_ID);
+        let (pump_amm, programs): (Vec<_>, Vec<_>) = programs
+            .into_iter()
+            .partition(|program| program == PUMP_AMM_PROGRAM_ID);
+        for (name, accounts) in [
+            (PUMP_FILTER, &pump),
+            (PUMP_AMM_FILTER, &pump_amm),
+            (PROGRAMS_FILTER, &programs),
+            (WALLETS_FILTER, &self.wallets),
+            (MINTS_FILTER, &self.mints),
+        ] {
+            if !accounts.is_empty() {
+                builder = builder.transactions(
+                    name,
+                    TransactionsFilter::new()
+                        .vote(false)
+                        .failed(false)
+                        .include_all(accounts.iter().cloned()),
+                );
+            }
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountArgs {
+    pub accounts: Vec<String>,
+    pub owners: Vec<String>,
+    pub commitment: CommitmentLevel,
+}
+
+impl AccountArgs {
+    pub fn request(&self) -> Result<SubscribeRequest, ClientError> {
+        let mut filter = AccountsFilter::new().accounts(self.accounts.iter().cloned());
+        for owner in &self.owners {
+            filter = filter.owner(owner.clone());
+        }
+        Ok(SubscribeRequestBuilder::new()
+            .accounts(ACCOUNTS_FILTER, filter)
+            .commitment(self.commitment)
+            .build()?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Watch {
+        filters: TransactionArgs,
+        // 多个端点时不支持，需要区分是否指定
+        format: Option<OutputFormat>,
+        checkpoint: Option<PathBuf>,
+    },
+    Decode {
+        payloads: Vec<String>,
+        file: Option<PathBuf>,
+        // 内置事件之外再尝试这些 Anchor IDL
+        idls: Vec<PathBuf>,
+        format: OutputFormat,
+    },
+    Record {
+        filters: TransactionArgs,
+        output: PathBuf,
+    },
+    Replay {
+        input: PathBuf,
+        format: OutputFormat,
+    },
+    Accounts {
+        filters: AccountArgs,
+        format: OutputFormat,
+    },
+    Run {
+        config: PathBuf,
+    },
+    Help,
+}
+
+// 不含程序名的命令行参数
+pub fn parse_args<I, S>(args: I) -> Result<Command, ClientError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut args = args.into_iter().map(Into::into);
+    let Some(command) = args.next() else {
+        return Ok(Command::Help);
+    };
+    let mut args = Args::parse(args)?;
+    if args.help {
+        return Ok(Command::Help);
+    }
+
+    let command = match command.as_str() {
+        "watch" => Command::Watch {
+            filters: args.transaction_args()?,
+            format: args.optional_format()?,
+            checkpoint: args.take_one("checkpoint")?.map(PathBuf::from),
+        },
+        "decode" => {
+            let payloads = std::mem::take(&mut args.positionals);
+            let file = args.take_one("file")?.map(PathBuf::from);
+            if payloads.is_empty() && file.is_none() {
+                return Err(usage_error("decode needs a payload or --file"));
+            }
+            Command::Decode {
+                payloads,
+                file,
+                idls: args
+                    .take_all("idl")
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .collect(),
+                format: args.format()?,
+            }
+        }
+        "record" => Command::Record {
+            filters: args.transaction_args()?,
+            output: args
+                .take_one("output")?
+                .map(PathBuf::from)
+                .ok_or_else(|| usage_error("record needs --output"))?,
+        },
+        "replay" => {
+            let input = args
+                .positionals
+                .pop()
+                .map(PathBuf::from)
+                .ok_or_else(|| usage_error("replay needs a recording file"))?;
+            Command::Replay {
+                input,
+                format: args.format()?,
+            }
+        }
+        "accounts" => {
+            let filters = AccountArgs {
+                accounts: args.take_all("account"),
+                owners: args.take_all("owner"),
+                commitment: args.commitment()?,
+            };
+            if filters.accounts.is_empty() && filters.owners.is_empty() {
+                return Err(usage_error("accounts needs --account or --owner"));
+            }
+            Command::Accounts {
+                filters,
+                format: args.format()?,
+            }
+        }
+        "run" => Command::Run {
+            config: args
+                .take_one("config")?
+                .map(PathBuf::from)
+                .ok_or_else(|| usage_error("run needs --config"))?,
+        },
+        "help" => Command::Help,
+        other => return Err(usage_error(&format!("unknown command {}", other))),
+    };
+    args.finish()?;
+    Ok(command)
+}
+
+fn usage_error(message: &str) -> ClientError {
+    ClientError::Config(message.to_string())
+}
+
+// 所有选项都带值，支持 --key value 和 --key=value
+#[derive(Debug, Default)]
+struct Args {
+    options: Vec<(String, String)>,
+    positionals: Vec<String>,
+    help: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ClientError> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                parsed.help = true;
+                continue;
+            }
+            let Some(option) = arg.strip_prefix("--") else {
+                parsed.positionals.push(arg);
+                continue;
+            };
+            let (key, value) = match option.split_once('=') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| usage_error(&format!("--{} needs a value", option)))?;
+                    (option.to_string(), value)
+                }
+            };
+            parsed.options.push((key, value));
+        }
+        Ok(parsed)
+    }
+
+    fn take_all(&mut self, key: &str) -> Vec<String> {
+        let (matched, rest) = std::mem::take(&mut self.options)
+            .into_iter()
+            .partition(|(k, _)| k == key);
+        self.options = rest;
+        matched.into_iter().map(|(_, value)| value).collect()
+    }
+
+    fn take_one(&mut self, key: &str) -> Result<Option<String>, ClientError> {
+        let mut values = self.take_all(key);
+        if values.len() > 1 {
+            return Err(usage_error(&format!("--{} given more than once", key)));
+        }
+        Ok(values.pop())
+    }
+
+    fn format(&mut self) -> Result<OutputFormat, ClientError> {
+        Ok(self.optional_format()?.unwrap_or_default())
+    }
+
+    fn optional_format(&mut self) -> Result<Option<OutputFormat>, ClientError> {
+        self.take_one("format")?
+            .map(|value| value.parse())
+            .transpose()
+    }
+
+    fn commitment(&mut self) -> Result<CommitmentLevel, ClientError> {
+        match self.take_one("commitment")?.as_deref() {
+            None | Some("processed") => Ok(CommitmentLevel::Processed),
+            Some("confirmed") => Ok(CommitmentLevel::Confirmed),
+            Some("finalized") => Ok(CommitmentLevel::Finalized),
+            Some(other) => Err(usage_error(&format!("unknown commitment {}", other))),
+        }
+    }
+
+    fn transaction_args(&mut self) -> Result<TransactionArgs, ClientError> {
+        let filters = TransactionArgs {
+            programs: self.take_all("program"),
+            wallets: self.take_all("wallet"),
+            mints: self.take_all("mint"),
+            commitment: self.commitment()?,
+        };
+        if filters.programs.is_empty() && filters.wallets.is_empty() && filters.mints.is_empty() {
+            return Err(usage_error(
+                "at least one --program, --wallet or --mint is required",
+            ));
+        }
+        Ok(filters)
+    }
+
+    // 还有没用到的参数说明拼写错误或者不支持
+    fn finish(self) -> Result<(), ClientError> {
+        if let Some((key, _)) = self.options.first() {
+            return Err(usage_error(&format!("unknown option --{}", key)));
+        }
+        if let Some(arg) = self.positionals.first() {
+            return Err(usage_error(&format!("unexpected argument {}", arg)));
+        }
+        Ok(())
+    }
+}
+
+// watch 的所有过滤器交给同一个 printer：pump / pump_amm 只运行各自的解析器，
+// 钱包、mint 和其他程序的交易运行全部解析器；同时匹配多个过滤器的交易只输出一次
+pub fn watch_router<W: Write + Send + 'static>(printer: EventPrinter<W>) -> FilterRouter {
+    let printer = printer
+        .with_filter_decoder(
+            PUMP_FILTER,
+            EventHandler::new().with_decoder(Decoder::Pump, [PUMP_PROGRAM_ID]),
+        )
+        .with_filter_decoder(
+            PUMP_AMM_FILTER,
+            EventHandler::new().with_decoder(Decoder::PumpAmm, [PUMP_AMM_PROGRAM_ID]),
+        );
+    FilterRouter::new().route_filters(
+        [
+            PUMP_FILTER,
+            PUMP_AMM_FILTER,
+            WALLETS_FILTER,
+            MINTS_FILTER,
+            PROGRAMS_FILTER,
+        ],
+        printer,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUMP: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+
+    fn parse(args: &str) -> Result<Command, ClientError> {
+        parse_args(args.split_whitespace())
+    }
+
+    #[test]
+    fn test_parse_watch() {
+        let command = parse(&format!(
+            "watch --program {} --wallet=BVdVonejnHwKAVFKx1YpQaBc8t225hFuzjns5ZMEq3Pp --format json",
+            PUMP
+        ))
+        .unwrap();
+        let Command::Watch {
+            filters, format, ..
+        } = command
+        else {
+            panic!("expected watch");
+        };
+        assert_eq!(format, Some(OutputFormat::Json));
+        assert_eq!(filters.programs, vec![PUMP]);
+
+        let request = filters.request().unwrap();
+        assert!(request.transactions.contains_key(PUMP_FILTER));
+        assert!(!request.transactions.contains_key(PROGRAMS_FILTER));
+        assert!(request.transactions.contains_key(WALLETS_FILTER));
+        assert!(!request.transactions.contains_key(MINTS_FILTER));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("watch --format json").is_err());
+        assert!(parse(&format!("watch --program {} --programs x", PUMP)).is_err());
+        assert!(parse("decode --format xml abc").is_err());
+        assert_eq!(parse("replay --help").unwrap(), Command::Help);
+    }
+
+    #[tokio::test]
+    async fn test_watch_prints_each_transaction_once() {
+        use crate::{
+            engine::UpdateDispatcher,
+            mock,
+            model::{EventTrait, pumpfun_model::TradeEvent},
+            output::OutputWriter,
+        };
+
+        let printer = EventPrinter::new(OutputWriter::new(OutputFormat::Json, vec![]));
+        let output = printer.share_output();
+        let mut router = watch_router(printer);
+
+        let logs = vec![
+            format!("Program {} invoke [1]", PUMP),
+            mock::program_data_log(
+                TradeEvent::discriminator(),
+                &borsh::to_vec(&TradeEvent::default()).unwrap(),
+            ),
+            format!("Program {} success", PUMP),
+        ];
+        // 钱包的 Pump 交易同时匹配 pump 和 wallets 两个过滤器
+        let mut update = mock::transaction_update(1, &[3u8; 64], logs);
+        update.filters = vec![PUMP_FILTER.to_string(), WALLETS_FILTER.to_string()];
+        router.on_update(update).await.unwrap();
+        drop(router);
+
+        let output = String::from_utf8(output.into_inner().unwrap()).unwrap();
+        let rows: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["type"], TradeEvent::NAME);
+        assert_eq!(rows[0]["filters"], "pump|wallets");
+    }
+}
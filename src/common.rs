@@ -1,7 +1,5 @@
 #[cfg(test)]
 mod tests {
-    use solana_sdk::bs58::encode;
-
     #[test]
     fn test_encode() {
         // let dis = vec![
@@ -0,0 +1,189 @@
+use std::{env, fs, path::PathBuf};
+
+use yellowstone_grpc_client::ClientTlsConfig;
+use yellowstone_grpc_proto::tonic::transport::{Certificate, Identity};
+
+use crate::error::ClientError;
+
+const ENV_GRPC_URL: &str = "YELLOWSTONE_GRPC_URL";
+const ENV_X_TOKEN: &str = "YELLOWSTONE_X_TOKEN";
+const ENV_X_TOKEN_FILE: &str = "YELLOWSTONE_X_TOKEN_FILE";
+const ENV_PLAINTEXT: &str = "YELLOWSTONE_PLAINTEXT";
+const ENV_CA_CERT: &str = "YELLOWSTONE_CA_CERT";
+const ENV_CLIENT_CERT: &str = "YELLOWSTONE_CLIENT_CERT";
+const ENV_CLIENT_KEY: &str = "YELLOWSTONE_CLIENT_KEY";
+const ENV_TLS_DOMAIN: &str = "YELLOWSTONE_TLS_DOMAIN";
+
+#[derive(Clone, Debug, Default)]
+pub struct TlsOptions {
+    // 强制明文连接；http:// 开头的地址默认就是明文
+    pub plaintext: bool,
+    // 自定义 CA 证书（PEM），不设置时使用系统根证书
+    pub ca_cert: Option<PathBuf>,
+    // mTLS 客户端证书和私钥（PEM），需要同时设置
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub domain_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GrpcConfig {
+    pub endpoint: String,
+    pub x_token: Option<String>,
+    pub tls: TlsOptions,
+}
+
+impl GrpcConfig {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_env() -> Result<Self, ClientError> {
+        Ok(Self::from_env_multi()?.remove(0))
+    }
+
+    // YELLOWSTONE_GRPC_URL 可以用逗号分隔多个端点，YELLOWSTONE_X_TOKEN 同样按顺序对应，
+    // 只有一个 token 时所有端点共用
+    pub fn from_env_multi() -> Result<Vec<Self>, ClientError> {
+        let endpoints = env_var(ENV_GRPC_URL)
+            .map(|urls| split_list(&urls))
+            .filter(|urls| !urls.is_empty())
+            .ok_or_else(|| ClientError::Config(format!("{} must be set", ENV_GRPC_URL)))?;
+
+        let x_tokens = match (env_var(ENV_X_TOKEN), env_var(ENV_X_TOKEN_FILE)) {
+            (Some(tokens), _) => split_list(&tokens),
+            (None, Some(paths)) => split_list(&paths)
+                .iter()
+                .map(|path| read_token_file(path))
+                .collect::<Result<_, _>>()?,
+            (None, None) => vec![],
+        };
+        if x_tokens.len() > 1 && x_tokens.len() != endpoints.len() {
+            return Err(ClientError::Config(format!(
+                "{} has {} tokens but {} endpoints are configured",
+                ENV_X_TOKEN,
+                x_tokens.len(),
+                endpoints.len()
+            )));
+        }
+
+        let tls = TlsOptions {
+            plaintext: env_var(ENV_PLAINTEXT).is_some_and(|v| matches!(v.as_str(), "1" | "true")),
+            ca_cert: env_var(ENV_CA_CERT).map(PathBuf::from),
+            client_cert: env_var(ENV_CLIENT_CERT).map(PathBuf::from),
+            client_key: env_var(ENV_CLIENT_KEY).map(PathBuf::from),
+            domain_name: env_var(ENV_TLS_DOMAIN),
+        };
+
+        Ok(endpoints
+            .into_iter()
+            .enumerate()
+            .map(|(i, endpoint)| Self {
+                endpoint,
+                x_token: x_tokens.get(i).or(x_tokens.first()).cloned(),
+                tls: tls.clone(),
+            })
+            .collect())
+    }
+}
+
+impl TlsOptions {
+    pub fn is_plaintext(&self, endpoint: &str) -> bool {
+        self.plaintext || endpoint.starts_with("http://")
+    }
+
+    // 返回 None 表示明文连接
+    pub fn client_tls_config(
+        &self,
+        endpoint: &str,
+    ) -> Result<Option<ClientTlsConfig>, ClientError> {
+        if self.is_plaintext(endpoint) {
+            if endpoint.starts_with("https://") {
+                return Err(ClientError::Config(format!(
+                    "plaintext requested for https endpoint {}",
+                    endpoint
+                )));
+            }
+            return Ok(None);
+        }
+
+        let mut config = ClientTlsConfig::new();
+        config = match &self.ca_cert {
+            Some(path) => config.ca_certificate(Certificate::from_pem(read_file(path)?)),
+            None => config.with_native_roots(),
+        };
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                config = config.identity(Identity::from_pem(read_file(cert)?, read_file(key)?));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(ClientError::Config(
+                    "client certificate and key must be set together".to_string(),
+                ));
+            }
+        }
+
+        if let Some(domain_name) = &self.domain_name {
+            config = config.domain_name(domain_name);
+        }
+
+        Ok(Some(config))
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn read_file(path: &PathBuf) -> Result<Vec<u8>, ClientError> {
+    fs::read(path)
+        .map_err(|e| ClientError::Config(format!("failed to read {}: {}", path.display(), e)))
+}
+
+pub fn read_token_file(path: &str) -> Result<String, ClientError> {
+    let token = fs::read_to_string(path)
+        .map_err(|e| ClientError::Config(format!("failed to read x-token file {}: {}", path, e)))?;
+    Ok(token.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plaintext_endpoint() {
+        let tls = TlsOptions::default();
+        assert!(
+            tls.client_tls_config("http://localhost:10000")
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            tls.client_tls_config("https://solana-yellowstone-grpc.publicnode.com")
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_client_identity_requires_key() {
+        let tls = TlsOptions {
+            client_cert: Some(PathBuf::from("client.pem")),
+            ..Default::default()
+        };
+        assert!(tls.client_tls_config("https://example.com").is_err());
+    }
+}
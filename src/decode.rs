@@ -0,0 +1,285 @@
+use base64::{Engine, engine::general_purpose};
+use solana_sdk::bs58;
+
+use crate::{
+    error::ClientError,
+    idl::IdlDecoder,
+    model::{EventTrait, PROGRAM_DATA},
+};
+
+// emit_cpi! 产生的内部指令数据以这个 tag 开头，后面才是事件的 discriminator
+pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+// 已注册的事件解析器，#[derive(SolanaEvent)] 会自动注册，discriminator 在编译期保证不重复
+pub struct RegisteredEvent {
+    pub event_type: &'static str,
+    pub discriminator: [u8; 8],
+    decode: fn(&[u8]) -> Result<String, ClientError>,
+}
+
+impl RegisteredEvent {
+    #[doc(hidden)]
+    pub const fn new<T: EventTrait>() -> Self {
+        Self {
+            event_type: T::NAME,
+            discriminator: T::DISCRIMINATOR,
+            decode: decode_debug::<T>,
+        }
+    }
+}
+
+fn decode_debug<T: EventTrait>(bytes: &[u8]) -> Result<String, ClientError> {
+    T::from_bytes(bytes).map(|event| format!("{:?}", event))
+}
+
+inventory::collect!(RegisteredEvent);
+
+// 按事件类型排序
+pub fn registered_events() -> Vec<&'static RegisteredEvent> {
+    let mut events: Vec<_> = inventory::iter::<RegisteredEvent>.into_iter().collect();
+    events.sort_by_key(|event| event.event_type);
+    events
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeOutcome {
+    Matched { event_type: String, fields: String },
+    // 没有解析出事件的原因
+    Unmatched(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedPayload {
+    pub payload: String,
+    pub discriminator: Option<[u8; 8]>,
+    pub outcome: DecodeOutcome,
+}
+
+impl DecodedPayload {
+    pub fn discriminator_hex(&self) -> String {
+        self.discriminator
+            .map(|discr| discr.iter().map(|b| format!("{:02x}", b)).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn discriminator_base58(&self) -> String {
+        self.discriminator
+            .map(|discr| bs58::encode(discr).into_string())
+            .unwrap_or_default()
+    }
+
+    // 输出用的 (键, 值)
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let (event_type, fields, reason) = match &self.outcome {
+            DecodeOutcome::Matched { event_type, fields } => {
+                (event_type.clone(), fields.clone(), String::new())
+            }
+            DecodeOutcome::Unmatched(reason) => ("none".to_string(), String::new(), reason.clone()),
+        };
+        vec![
+            ("payload", self.payload.clone()),
+            ("type", event_type),
+            ("discriminator", self.discriminator_hex()),
+            ("discriminator_base58", self.discriminator_base58()),
+            ("fields", fields),
+            ("reason", reason),
+        ]
+    }
+}
+
+// 解析单个 base64 payload，可以带 "Program data: " 前缀
+pub fn decode_payload(payload: &str) -> DecodedPayload {
+    decode_payload_with(payload, &[])
+}
+
+// 内置的事件解析失败或不认识时，再按顺序尝试 IDL
+pub fn decode_payload_with(payload: &str, idls: &[IdlDecoder]) -> DecodedPayload {
+    let payload = payload.trim();
+    let payload = payload.strip_prefix(PROGRAM_DATA).unwrap_or(payload).trim();
+    let unmatched = |discriminator, reason: String| DecodedPayload {
+        payload: payload.to_string(),
+        discriminator,
+        outcome: DecodeOutcome::Unmatched(reason),
+    };
+
+    let bytes = match general_purpose::STANDARD.decode(payload) {
+        Ok(bytes) => bytes,
+        Err(e) => return unmatched(None, format!("not valid base64: {}", e)),
+    };
+    let bytes = bytes.strip_prefix(&EVENT_IX_TAG[..]).unwrap_or(&bytes);
+    let Some((discr, rest)) = bytes.split_first_chunk::<8>() else {
+        return unmatched(
+            None,
+            format!(
+                "payload is {} bytes, shorter than the 8-byte discriminator",
+                bytes.len()
+            ),
+        );
+    };
+    let matched = |event_type: String, fields: String| DecodedPayload {
+        payload: payload.to_string(),
+        discriminator: Some(*discr),
+        outcome: DecodeOutcome::Matched { event_type, fields },
+    };
+
+    let events = registered_events();
+    let mut reasons = vec![];
+    if let Some(event) = events.iter().find(|event| event.discriminator == *discr) {
+        match (event.decode)(rest) {
+            Ok(fields) => return matched(event.event_type.to_string(), fields),
+            // 通常是程序升级后事件结构变了
+            Err(e) => reasons.push(format!(
+                "discriminator matches {} but the {} byte body does not decode: {}",
+                event.event_type,
+                rest.len(),
+                e
+            )),
+        }
+    }
+    for idl in idls {
+        match idl.decode_event(bytes) {
+            Ok(Some(event)) => {
+                return matched(
+                    format!("{}:{}", idl.name(), event.name),
+                    event.value.to_string(),
+                );
+            }
+            Ok(None) => {}
+            Err(e) => reasons.push(format!("IDL {} does not decode: {}", idl.name(), e)),
+        }
+    }
+
+    if reasons.is_empty() {
+        let known: Vec<String> = events
+            .iter()
+            .map(|event| event.event_type.to_string())
+            .chain(idls.iter().flat_map(|idl| {
+                idl.events()
+                    .map(|(name, _)| format!("{}:{}", idl.name(), name))
+            }))
+            .collect();
+        reasons.push(format!(
+            "discriminator does not match any registered event ({})",
+            known.join(", ")
+        ));
+    }
+    unmatched(Some(*discr), reasons.join("; "))
+}
+
+pub fn decode_input(input: &str) -> Result<Vec<DecodedPayload>, ClientError> {
+    decode_input_with(input, &[])
+}
+
+// 输入可以是 JSON 格式的日志数组，也可以是每行一条日志或 base64 payload；
+// 日志中只解析 "Program data: " 行
+pub fn decode_input_with(
+    input: &str,
+    idls: &[IdlDecoder],
+) -> Result<Vec<DecodedPayload>, ClientError> {
+    let input = input.trim();
+    let lines: Vec<String> = if input.starts_with('[') {
+        serde_json::from_str(input)
+            .map_err(|e| ClientError::Decode(format!("invalid JSON log array: {}", e)))?
+    } else {
+        input.lines().map(str::to_string).collect()
+    };
+
+    Ok(lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| match line.split_once(PROGRAM_DATA) {
+            Some((_, payload)) => Some(decode_payload_with(payload, idls)),
+            // 其他日志行（invoke / success / Program log）跳过
+            None if line.starts_with("Program ") => None,
+            None => Some(decode_payload_with(line, idls)),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{
+        pumpamm::{BuyEvent, CreatePoolEvent, SellEvent},
+        pumpfun_model::{CompleteEvent, CreateEvent, TradeEvent},
+    };
+    use borsh::BorshSerialize;
+
+    fn encode<T: EventTrait + BorshSerialize>(event: &T) -> String {
+        let mut bytes = T::discriminator().to_vec();
+        event.serialize(&mut bytes).unwrap();
+        general_purpose::STANDARD.encode(bytes)
+    }
+
+    #[test]
+    fn test_decode_payload() {
+        let trade = TradeEvent {
+            sol_amount: 1_000,
+            is_buy: true,
+            ..Default::default()
+        };
+        let decoded = decode_payload(&encode(&trade));
+        assert_eq!(
+            decoded.outcome,
+            DecodeOutcome::Matched {
+                event_type: "pump_trade".to_string(),
+                fields: format!("{:?}", trade),
+            }
+        );
+        assert_eq!(decoded.discriminator_hex(), "bddb7fd34ee661ee");
+
+        let mut cpi = EVENT_IX_TAG.to_vec();
+        cpi.extend(general_purpose::STANDARD.decode(encode(&trade)).unwrap());
+        let decoded = decode_payload(&general_purpose::STANDARD.encode(cpi));
+        assert!(matches!(decoded.outcome, DecodeOutcome::Matched { .. }));
+    }
+
+    #[test]
+    fn test_explain_unmatched() {
+        let reason = |payload: &str| match decode_payload(payload).outcome {
+            DecodeOutcome::Unmatched(reason) => reason,
+            outcome => panic!("unexpected {:?}", outcome),
+        };
+        assert!(reason("not base64!").contains("not valid base64"));
+        assert!(reason("AAAA").contains("shorter than the 8-byte discriminator"));
+        assert!(reason("AAAAAAAAAAAAAAAA").contains("does not match any registered event"));
+
+        let truncated = &encode(&CreateEvent::default())[..16];
+        assert!(reason(truncated).contains("matches pump_create but"));
+    }
+
+    #[test]
+    fn test_decode_log_array() {
+        let logs = serde_json::json!([
+            "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P invoke [1]",
+            format!("Program data: {}", encode(&CompleteEvent::default())),
+            "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P success",
+        ]);
+        let decoded = decode_input(&logs.to_string()).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert!(matches!(
+            &decoded[0].outcome,
+            DecodeOutcome::Matched { event_type, .. } if event_type == "pump_complete"
+        ));
+        assert!(decode_input("[not json").is_err());
+    }
+
+    #[test]
+    fn test_derived_events_are_registered() {
+        let names: Vec<_> = registered_events()
+            .iter()
+            .map(|event| event.event_type)
+            .collect();
+        for name in [
+            CreateEvent::NAME,
+            CompleteEvent::NAME,
+            TradeEvent::NAME,
+            BuyEvent::NAME,
+            SellEvent::NAME,
+            CreatePoolEvent::NAME,
+        ] {
+            assert!(names.contains(&name), "{} is not registered", name);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::future::Future;
+
+use yellowstone_grpc_proto::{
+    geyser::{
+        SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdateBlock, SubscribeUpdateBlockMeta,
+        SubscribeUpdateEntry, SubscribeUpdateSlot, SubscribeUpdateTransaction,
+        SubscribeUpdateTransactionStatus, subscribe_update::UpdateOneof,
+    },
+    prost_types::Timestamp,
+};
+
+use crate::error::ClientError;
+
+pub type DispatchResult = Result<(), ClientError>;
+
+// 每条更新的公共信息
+#[derive(Clone, Debug, Default)]
+pub struct UpdateContext {
+    // 匹配到的过滤器名称
+    pub filters: Vec<String>,
+    // 服务端生成这条更新的时间
+    pub created_at: Option<Timestamp>,
+}
+
+// 按 UpdateOneof 分发更新，只需实现关心的类型，其余默认忽略
+pub trait UpdateDispatcher: Send {
+    fn on_account(
+        &mut self,
+        _ctx: &UpdateContext,
+        _account: SubscribeUpdateAccount,
+    ) -> impl Future<Output = DispatchResult> + Send {
+        async { Ok(()) }
+    }
+
+    fn on_slot(
+        &mut self,
+        _ctx: &UpdateContext,
+        _slot: SubscribeUpdateSlot,
+    ) -> impl Future<Output = DispatchResult> + Send {
+        async { Ok(()) }
+    }
+
+    fn on_transaction(
+        &mut self,
+        _ctx: &UpdateContext,
+        _transaction: SubscribeUpdateTransaction,
+    ) -> impl Future<Output = DispatchResult> + Send {
+        async { Ok(()) }
+    }
+
+    fn on_transaction_status(
+        &mut self,
+        _ctx: &UpdateContext,
+        _status: SubscribeUpdateTransactionStatus,
+    ) -> impl Future<Output = DispatchResult> + Send {
+        async { Ok(()) }
+    }
+
+    fn on_block(
+        &mut self,
+        _ctx: &UpdateContext,
+        _block: SubscribeUpdateBlock,
+    ) -> impl Future<Output = DispatchResult> + Send {
+        async { Ok(()) }
+    }
+
+    fn on_block_meta(
+        &mut self,
+        _ctx: &UpdateContext,
+        _block_meta: SubscribeUpdateBlockMeta,
+    ) -> impl Future<Output = DispatchResult> + Send {
+        async { Ok(()) }
+    }
+
+    fn on_entry(
+        &mut self,
+        _ctx: &UpdateContext,
+        _entry: SubscribeUpdateEntry,
+    ) -> impl Future<Output = DispatchResult> + Send {
+        async { Ok(()) }
+    }
+
+    // 默认按 UpdateOneof 分发；需要整条更新时（例如按过滤器路由）可以覆盖
+    fn on_update(&mut self, update: SubscribeUpdate) -> impl Future<Output = DispatchResult> + Send
+    where
+        Self: Sized,
+    {
+        dispatch(self, update)
+    }
+
+    // 订阅结束、队列处理完之后调用，用于写出缓存的数据
+    fn flush(&mut self) -> impl Future<Output = DispatchResult> + Send {
+        async { Ok(()) }
+    }
+}
+
+// 每条更新依次交给所有处理器，一个出错不影响其他处理器
+impl<D: UpdateDispatcher> UpdateDispatcher for Vec<D> {
+    async fn on_update(&mut self, update: SubscribeUpdate) -> DispatchResult {
+        let mut errors = vec![];
+        for dispatcher in self.iter_mut() {
+            if let Err(e) = dispatcher.on_update(update.clone()).await {
+                errors.push(e.to_string());
+            }
+        }
+        into_result(errors)
+    }
+
+    async fn flush(&mut self) -> DispatchResult {
+        let mut errors = vec![];
+        for dispatcher in self.iter_mut() {
+            if let Err(e) = dispatcher.flush().await {
+                errors.push(e.to_string());
+            }
+        }
+        into_result(errors)
+    }
+}
+
+// 多个处理器的错误合并成一个
+pub(crate) fn into_result(errors: Vec<String>) -> DispatchResult {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ClientError::Handler(errors.join("; ")))
+    }
+}
+
+// ping / pong 由订阅循环处理，不会到这里
+pub async fn dispatch<D: UpdateDispatcher>(
+    dispatcher: &mut D,
+    update: SubscribeUpdate,
+) -> DispatchResult {
+    let ctx = UpdateContext {
+        filters: update.filters,
+        created_at: update.created_at,
+    };
+    match update.update_oneof {
+        Some(UpdateOneof::Account(account)) => dispatcher.on_account(&ctx, account).await,
+        Some(UpdateOneof::Slot(slot)) => dispatcher.on_slot(&ctx, slot).await,
+        Some(UpdateOneof::Transaction(transaction)) => {
+            dispatcher.on_transaction(&ctx, transaction).await
+        }
+        Some(UpdateOneof::TransactionStatus(status)) => {
+            dispatcher.on_transaction_status(&ctx, status).await
+        }
+        Some(UpdateOneof::Block(block)) => dispatcher.on_block(&ctx, block).await,
+        Some(UpdateOneof::BlockMeta(block_meta)) => {
+            dispatcher.on_block_meta(&ctx, block_meta).await
+        }
+        Some(UpdateOneof::Entry(entry)) => dispatcher.on_entry(&ctx, entry).await,
+        Some(UpdateOneof::Ping(_)) | Some(UpdateOneof::Pong(_)) | None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yellowstone_grpc_proto::geyser::SubscribeUpdatePing;
+
+    #[derive(Default)]
+    struct Counter {
+        slots: Vec<u64>,
+        transactions: usize,
+    }
+
+    impl UpdateDispatcher for Counter {
+        async fn on_slot(
+            &mut self,
+            ctx: &UpdateContext,
+            slot: SubscribeUpdateSlot,
+        ) -> DispatchResult {
+            assert_eq!(ctx.filters, vec!["slots"]);
+            self.slots.push(slot.slot);
+            Ok(())
+        }
+
+        async fn on_transaction(
+            &mut self,
+            _ctx: &UpdateContext,
+            _transaction: SubscribeUpdateTransaction,
+        ) -> DispatchResult {
+            self.transactions += 1;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_by_variant() {
+        let mut counter = Counter::default();
+        let updates = [
+            UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot: 7,
+                ..Default::default()
+            }),
+            UpdateOneof::Transaction(SubscribeUpdateTransaction::default()),
+            UpdateOneof::Ping(SubscribeUpdatePing {}),
+            UpdateOneof::Entry(SubscribeUpdateEntry::default()),
+        ];
+        for update in updates {
+            let update = SubscribeUpdate {
+                filters: vec!["slots".to_string()],
+                update_oneof: Some(update),
+                ..Default::default()
+            };
+            dispatch(&mut counter, update).await.unwrap();
+        }
+        assert_eq!(counter.slots, vec![7]);
+        assert_eq!(counter.transactions, 1);
+    }
+}
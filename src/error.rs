@@ -0,0 +1,138 @@
+use std::io;
+
+use thiserror::Error;
+use yellowstone_grpc_client::{GeyserGrpcBuilderError, GeyserGrpcClientError};
+use yellowstone_grpc_proto::tonic::{Code, Status, transport};
+
+use crate::request::RequestError;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("transport error: {0}")]
+    Transport(#[from] transport::Error),
+    // 流被关闭、长时间无数据或发送请求失败
+    #[error("disconnected: {0}")]
+    Disconnected(String),
+    // Status 较大，装箱避免 Result 过大
+    #[error("gRPC status {}: {}", .0.code(), .0.message())]
+    Status(Box<Status>),
+    // x-token 无效或没有权限
+    #[error("authentication failed: {0}")]
+    Auth(String),
+    #[error("configuration error: {0}")]
+    Config(String),
+    #[error("invalid subscribe request: {0}")]
+    Request(#[from] RequestError),
+    #[error("decode error: {0}")]
+    Decode(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    // 处理器返回的错误，多个处理器出错时合并在一起
+    #[error("handler error: {0}")]
+    Handler(String),
+    #[error("giving up after {attempts} reconnect attempts: {last}")]
+    RetriesExhausted {
+        attempts: u32,
+        last: Box<ClientError>,
+    },
+}
+
+impl ClientError {
+    // 可重试的错误由重连逻辑处理，其余的直接返回给调用方
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(_) | Self::Disconnected(_) => true,
+            Self::Status(status) => matches!(
+                status.code(),
+                Code::Unavailable
+                    | Code::DeadlineExceeded
+                    | Code::ResourceExhausted
+                    | Code::Aborted
+                    | Code::Internal
+                    | Code::Unknown
+                    | Code::Cancelled
+            ),
+            Self::Auth(_)
+            | Self::Config(_)
+            | Self::Request(_)
+            | Self::Decode(_)
+            | Self::Io(_)
+            | Self::Handler(_)
+            | Self::RetriesExhausted { .. } => false,
+        }
+    }
+
+    pub fn code(&self) -> Option<Code> {
+        match self {
+            Self::Status(status) => Some(status.code()),
+            _ => None,
+        }
+    }
+
+    // 建立连接时的 tonic 错误（连接被拒绝、超时等）可以重试
+    pub(crate) fn from_connect(error: GeyserGrpcBuilderError) -> Self {
+        match error {
+            GeyserGrpcBuilderError::TonicError(e) => Self::Transport(e),
+            other => other.into(),
+        }
+    }
+}
+
+impl From<Status> for ClientError {
+    fn from(status: Status) -> Self {
+        match status.code() {
+            Code::Unauthenticated | Code::PermissionDenied => {
+                Self::Auth(status.message().to_string())
+            }
+            _ => Self::Status(Box::new(status)),
+        }
+    }
+}
+
+impl From<GeyserGrpcBuilderError> for ClientError {
+    fn from(error: GeyserGrpcBuilderError) -> Self {
+        match error {
+            GeyserGrpcBuilderError::MetadataValueError(e) => {
+                Self::Config(format!("invalid x-token: {}", e))
+            }
+            // 构建时的 tonic 错误是 URL、TLS 等配置问题，重连也无法恢复
+            GeyserGrpcBuilderError::TonicError(e) => {
+                Self::Config(format!("invalid endpoint: {}", e))
+            }
+        }
+    }
+}
+
+impl From<GeyserGrpcClientError> for ClientError {
+    fn from(error: GeyserGrpcClientError) -> Self {
+        match error {
+            GeyserGrpcClientError::TonicStatus(status) => status.into(),
+            GeyserGrpcClientError::SubscribeSendError(e) => Self::Disconnected(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_classification() {
+        assert!(ClientError::from(Status::unavailable("restarting")).is_retryable());
+        assert!(!ClientError::from(Status::invalid_argument("bad filter")).is_retryable());
+
+        let auth = ClientError::from(Status::unauthenticated("invalid x-token"));
+        assert!(matches!(auth, ClientError::Auth(_)));
+        assert!(!auth.is_retryable());
+    }
+
+    #[test]
+    fn test_builder_errors_are_fatal() {
+        let error = yellowstone_grpc_client::GeyserGrpcClient::build_from_shared("not a url")
+            .map(|_| ())
+            .map_err(ClientError::from)
+            .unwrap_err();
+        assert!(matches!(error, ClientError::Config(_)));
+        assert!(!error.is_retryable());
+    }
+}
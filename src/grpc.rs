@@ -1,340 +1,777 @@
-use borsh::error;
-use futures_util::{SinkExt, StreamExt, lock, sink::Fanout};
-use log::{debug, error, info};
-use solana_sdk::{account, bs58};
-use std::{collections::HashMap, iter::zip, sync::Arc, time::Duration};
-use tokio::sync::Mutex;
-use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
-use yellowstone_grpc_proto::geyser::{
-    CommitmentLevel, PingRequest, SubscribeRequest, SubscribeRequestAccountsDataSlice,
-    SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions, SubscribeRequestPing,
-    SubscribeUpdateTransaction, subscribe_update::UpdateOneof,
+use futures_util::{Sink, SinkExt, StreamExt};
+use log::{debug, error, info, warn};
+use std::{
+    future::Future,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    sync::{Mutex, broadcast},
+    time::MissedTickBehavior,
+};
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient, Interceptor};
+use yellowstone_grpc_proto::{
+    geyser::{
+        CommitmentLevel, SubscribeRequest, SubscribeUpdate, SubscribeUpdateSlot,
+        subscribe_update::UpdateOneof,
+    },
+    tonic::Code,
 };
 
-use crate::handle::EventHandler;
+use crate::{
+    checkpoint::{CheckpointConfig, Checkpointer},
+    config::{GrpcConfig, TlsOptions},
+    engine::UpdateDispatcher,
+    error::ClientError,
+    handle::{EventHandler, TokenBalanceLogger, UpdateLogger},
+    latency::LatencyTracker,
+    lifecycle::{CommitmentTracker, LifecycleNotification},
+    pipeline::{self, PipelineConfig, QueueMetrics},
+    reconnect::{
+        PingTracker, ReconnectPolicy, ReplayDedup, SubscriptionEvent, SubscriptionMetrics,
+        WatchdogConfig,
+    },
+    request::transactions_request,
+    rpc::GeyserRpc,
+    shutdown::CancellationToken,
+    slots::{GAP_DETECTOR_FILTER, GapDetectionConfig, GapKind, SlotGapDetector},
+    subscription::SubscriptionHandle,
+};
 
 const CONNECT_TIMEOUT: u64 = 10;
 const KEEP_ALIVE_TIMEOUT: u64 = 60;
+const EVENT_CHANNEL_SIZE: usize = 1024;
 
 #[derive(Clone)]
 pub struct YellowstoneGrpc {
     endpoint: String,
-    #[allow(dead_code)]
     x_token: Option<String>,
+    // None 表示明文连接
+    tls_config: Option<ClientTlsConfig>,
     pub event_handler: Arc<Mutex<EventHandler>>,
-    // pub client:
+    reconnect_policy: ReconnectPolicy,
+    watchdog: WatchdogConfig,
+    metrics: Arc<SubscriptionMetrics>,
+    events: broadcast::Sender<SubscriptionEvent>,
+    pipeline: PipelineConfig,
+    queue_metrics: Arc<QueueMetrics>,
+    gap_detection: Option<GapDetectionConfig>,
+    lifecycle: Option<broadcast::Sender<LifecycleNotification>>,
+    shutdown: CancellationToken,
+    checkpoint: Option<Checkpointer>,
+    latency: LatencyTracker,
+}
+
+// 单次连接的结束原因
+enum StreamOutcome {
+    Disconnected {
+        error: ClientError,
+        received: bool,
+    },
+    ReplayUnavailable {
+        reason: String,
+        to_slot: Option<u64>,
+    },
+    // 发现丢失的 slot，需要从 from_slot 重新订阅
+    Backfill {
+        from_slot: u64,
+        to_slot: u64,
+    },
+    // 收到退出信号
+    Shutdown,
 }
 
 impl YellowstoneGrpc {
     pub fn new(endpoint: String, x_token: Option<String>) -> Self {
+        let tls_config = if TlsOptions::default().is_plaintext(&endpoint) {
+            None
+        } else {
+            Some(ClientTlsConfig::new().with_native_roots())
+        };
+        Self::with_tls(endpoint, x_token, tls_config)
+    }
+
+    pub fn from_config(config: GrpcConfig) -> Result<Self, ClientError> {
+        let tls_config = config.tls.client_tls_config(&config.endpoint)?;
+        Ok(Self::with_tls(config.endpoint, config.x_token, tls_config))
+    }
+
+    fn with_tls(
+        endpoint: String,
+        x_token: Option<String>,
+        tls_config: Option<ClientTlsConfig>,
+    ) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_SIZE);
+        let latency = LatencyTracker::new();
         Self {
             endpoint,
             x_token,
-            event_handler: Arc::new(Mutex::new(EventHandler::new())),
-            // client:
+            tls_config,
+            event_handler: Arc::new(Mutex::new(
+                EventHandler::new().with_latency(latency.clone()),
+            )),
+            reconnect_policy: ReconnectPolicy::default(),
+            watchdog: WatchdogConfig::default(),
+            metrics: Arc::new(SubscriptionMetrics::default()),
+            events,
+            pipeline: PipelineConfig::default(),
+            queue_metrics: Arc::new(QueueMetrics::default()),
+            gap_detection: None,
+            lifecycle: None,
+            shutdown: CancellationToken::new(),
+            checkpoint: None,
+            latency,
         }
     }
 
-    // fn get_client() {
-    //     GeyserGrpcClient::build_from_shared(self.endpoint.clone()).unwrap()
-    //         .tls_config(ClientTlsConfig::new().with_native_roots()).unwrap()
-    //         .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT))
-    //         .keep_alive_while_idle(true)
-    //         .timeout(Duration::from_secs(KEEP_ALIVE_TIMEOUT))
-    //         .connect()
-    //         .await.unwrap();
-    // }
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    pub fn with_watchdog(mut self, watchdog: WatchdogConfig) -> Self {
+        self.watchdog = watchdog;
+        self
+    }
+
+    pub fn with_pipeline(mut self, pipeline: PipelineConfig) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    // 额外订阅 slot 更新并检查连续性
+    pub fn with_gap_detection(mut self, config: GapDetectionConfig) -> Self {
+        self.gap_detection = Some(config);
+        self
+    }
+
+    // 事件在 processed 时立即上报，之后继续上报所在 slot 的 Confirmed / Finalized / RolledBack
+    pub fn with_lifecycle_tracking(mut self) -> Self {
+        let (tx, _) = broadcast::channel(EVENT_CHANNEL_SIZE);
+        self.event_handler = Arc::new(Mutex::new(
+            EventHandler::new()
+                .with_lifecycle(CommitmentTracker::new(tx.clone()))
+                .with_latency(self.latency.clone()),
+        ));
+        self.lifecycle = Some(tx);
+        self
+    }
+
+    // token 取消后订阅循环不再接收新数据，处理完队列中剩余的数据后返回 Ok
+    pub fn with_shutdown(mut self, token: CancellationToken) -> Self {
+        self.shutdown = token;
+        self
+    }
+
+    // 保存最后处理完的 slot，重启后从该 slot 继续订阅
+    pub fn with_checkpoint(mut self, config: CheckpointConfig) -> Self {
+        self.checkpoint = Some(Checkpointer::new(config));
+        self
+    }
+
+    pub fn checkpoint(&self) -> Option<&Checkpointer> {
+        self.checkpoint.as_ref()
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn metrics(&self) -> Arc<SubscriptionMetrics> {
+        self.metrics.clone()
+    }
+
+    // 读流与处理之间的队列深度、丢弃和落盘数量
+    pub fn queue_metrics(&self) -> Arc<QueueMetrics> {
+        self.queue_metrics.clone()
+    }
+
+    // created_at 到本地收到（按过滤器）以及到事件解析完成（按事件类型）的延迟
+    pub fn latency(&self) -> LatencyTracker {
+        self.latency.clone()
+    }
+
+    // 未开启 with_lifecycle_tracking 时返回 None
+    pub fn lifecycle_events(&self) -> Option<broadcast::Receiver<LifecycleNotification>> {
+        self.lifecycle.as_ref().map(broadcast::Sender::subscribe)
+    }
 
-    pub async fn subscribe(&self, program_id: String) -> Result<(), Box<dyn std::error::Error>> {
-        let client = GeyserGrpcClient::build_from_shared(self.endpoint.clone())?
-            .tls_config(ClientTlsConfig::new().with_native_roots())?
+    // 订阅重连、回放、丢失区间等事件
+    pub fn events(&self) -> broadcast::Receiver<SubscriptionEvent> {
+        self.events.subscribe()
+    }
+
+    async fn connect(&self) -> Result<GeyserGrpcClient<impl Interceptor + use<>>, ClientError> {
+        let mut builder = GeyserGrpcClient::build_from_shared(self.endpoint.clone())?
+            .x_token(self.x_token.clone())?;
+        if let Some(tls_config) = &self.tls_config {
+            builder = builder.tls_config(tls_config.clone())?;
+        }
+        builder
             .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT))
             .keep_alive_while_idle(true)
             .timeout(Duration::from_secs(KEEP_ALIVE_TIMEOUT))
             .connect()
-            .await?;
-
-        let client = Arc::new(Mutex::new(client));
-        let addrs = vec![program_id];
-        let subscribe_request = SubscribeRequest {
-            transactions: HashMap::from([(
-                "client".to_string(),
-                SubscribeRequestFilterTransactions {
-                    vote: Some(false),
-                    failed: Some(false),
-                    signature: None,
-                    account_include: addrs,
-                    account_exclude: vec![],
-                    account_required: vec![],
-                },
-            )]),
-            commitment: Some(CommitmentLevel::Processed.into()),
-            ..Default::default()
-        };
+            .await
+            .map_err(ClientError::from_connect)
+    }
 
-        let subscribe_request = SubscribeRequest {
-            transactions: HashMap::from([(
-                "".to_string(),
-                SubscribeRequestFilterTransactions {
-                    vote: Some(false),
-                    failed: Some(false),
-                    signature: None,
-                    account_include: vec![
-                        "BVdVonejnHwKAVFKx1YpQaBc8t225hFuzjns5ZMEq3Pp".to_string(),
-                    ],
-                    account_exclude: vec![],
-                    account_required: vec![],
-                },
-            )]),
-            commitment: Some(CommitmentLevel::Processed.into()),
-            ..Default::default()
-        };
+    // 一元 RPC（GetSlot、GetLatestBlockhash 等）客户端
+    pub async fn rpc(&self) -> Result<GeyserRpc<impl Interceptor + use<>>, ClientError> {
+        Ok(GeyserRpc::new(self.connect().await?))
+    }
+
+    async fn send_ping<S>(&self, subscribe_tx: &mut S, pings: &mut PingTracker)
+    where
+        S: Sink<SubscribeRequest> + Unpin,
+        S::Error: std::fmt::Debug,
+    {
+        match subscribe_tx.send(pings.next_ping(Instant::now())).await {
+            Ok(()) => {
+                self.metrics.pings_sent.fetch_add(1, Ordering::Relaxed);
+                debug!("Ping sent");
+            }
+            Err(e) => error!("Failed to send ping: {:?}", e),
+        }
+    }
+
+    fn emit(&self, event: SubscriptionEvent) {
+        match &event {
+            SubscriptionEvent::Connected { .. } | SubscriptionEvent::Recovered { .. } => {
+                info!("{:?}", event)
+            }
+            SubscriptionEvent::SlotGap(gap) if matches!(gap.kind, GapKind::Skipped) => {
+                debug!("{:?}", event)
+            }
+            _ => warn!("{:?}", event),
+        }
+        self.metrics.record(&event);
+        // 没有接收者时忽略
+        let _ = self.events.send(event);
+    }
 
-        let (mut subscribe_tx, mut stream) = client
-            .lock()
+    // 带自动重连的订阅循环：断线后按退避策略重连，并从最后处理的 slot 继续
+    pub async fn subscribe_supervised<F, Fut>(
+        &self,
+        request: SubscribeRequest,
+        on_update: F,
+    ) -> Result<(), ClientError>
+    where
+        F: FnMut(SubscribeUpdate) -> Fut,
+        Fut: Future<Output = Result<(), ClientError>>,
+    {
+        self.subscribe_with_handle(SubscriptionHandle::new(request), on_update)
             .await
-            .subscribe_with_request(Some(subscribe_request))
-            .await?;
+    }
 
-        while let Some(message) = stream.next().await {
-            match message {
-                Ok(msg) => match msg.update_oneof {
-                    Some(UpdateOneof::Transaction(sut)) => {
-                        if let Some(meta) = sut.transaction.clone().and_then(|t| t.meta) {
-                            let logs = meta.log_messages;
-                            if !logs.is_empty() {
-                                let slot = sut.slot;
-                                let signature = sut
-                                    .transaction
-                                    .and_then(|t| t.transaction)
-                                    .and_then(|t| t.signatures.first().cloned())
-                                    .map(|sig| bs58::encode(sig).into_string())
-                                    .unwrap_or_else(|| "unknown".to_string());
-
-                                let mut event_handler = self.event_handler.lock().await;
-                                event_handler.handle_logs(&logs, slot, signature).await?;
-                            }
-                        }
+    // 同 subscribe_supervised，但过滤条件可以通过 handle 在运行时修改
+    pub async fn subscribe_with_handle<F, Fut>(
+        &self,
+        handle: SubscriptionHandle,
+        mut on_update: F,
+    ) -> Result<(), ClientError>
+    where
+        F: FnMut(SubscribeUpdate) -> Fut,
+        Fut: Future<Output = Result<(), ClientError>>,
+    {
+        let result = self
+            .supervise(handle, |update| {
+                let slot = update.update_oneof.as_ref().and_then(update_slot);
+                let processed = on_update(update);
+                let checkpoint = self.checkpoint.clone();
+                async move {
+                    processed.await?;
+                    if let (Some(checkpoint), Some(slot)) = (checkpoint, slot) {
+                        checkpoint.record(slot);
                     }
-                    Some(UpdateOneof::Ping(_)) => {
-                        let _ = subscribe_tx
-                            .send(SubscribeRequest {
-                                ping: Some(SubscribeRequestPing { id: 1 }),
-                                ..Default::default()
-                            })
-                            .await;
-                        debug!("Ping sent");
+                    Ok(())
+                }
+            })
+            .await;
+        self.flush_checkpoint();
+        result
+    }
+
+    async fn supervise<F, Fut>(
+        &self,
+        handle: SubscriptionHandle,
+        mut on_update: F,
+    ) -> Result<(), ClientError>
+    where
+        F: FnMut(SubscribeUpdate) -> Fut,
+        Fut: Future<Output = Result<(), ClientError>>,
+    {
+        let mut attempt: u32 = 0;
+        // 回填会重新推送缺口之后已经处理过的 slot，去重窗口至少要覆盖回填区间
+        let mut delivered = ReplayDedup::new(
+            self.gap_detection
+                .as_ref()
+                .map_or(0, |config| config.max_backfill_slots),
+        );
+        let mut resume_slot = self.initial_slot(&handle).await;
+        let mut gap_detector = self
+            .gap_detection
+            .as_ref()
+            .map(|config| SlotGapDetector::new(config.status));
+
+        loop {
+            let error = match self
+                .run_stream(
+                    &handle,
+                    resume_slot,
+                    &mut on_update,
+                    &mut delivered,
+                    &mut gap_detector,
+                    attempt,
+                )
+                .await
+            {
+                StreamOutcome::Disconnected { error, received } => {
+                    self.emit(SubscriptionEvent::Disconnected {
+                        reason: error.to_string(),
+                        last_slot: delivered.last_slot(),
+                    });
+                    // 认证失败、请求非法等错误重连也无法恢复
+                    if !error.is_retryable() {
+                        return Err(error);
                     }
-                    _ => {}
-                },
-                Err(e) => {
-                    error!("Error: {:?}", e);
-                    break;
+                    if received {
+                        attempt = 0;
+                    }
+                    error
                 }
+                StreamOutcome::ReplayUnavailable { reason, to_slot } => {
+                    // 回放不可用时只能放弃 from_slot，从最新位置重新订阅
+                    warn!("from_slot {:?} rejected: {}", resume_slot, reason);
+                    if let Some(from_slot) = resume_slot {
+                        self.emit(SubscriptionEvent::Gap { from_slot, to_slot });
+                    }
+                    delivered.reset_slot();
+                    resume_slot = None;
+                    continue;
+                }
+                StreamOutcome::Backfill { from_slot, to_slot } => {
+                    // 回填不算断线，立即重新订阅；缺口之后的数据会被重复推送
+                    self.emit(SubscriptionEvent::Backfilling { from_slot, to_slot });
+                    resume_slot = Some(from_slot);
+                    continue;
+                }
+                StreamOutcome::Shutdown => {
+                    info!("Subscription to {} stopped by shutdown", self.endpoint);
+                    return Ok(());
+                }
+            };
+
+            // 从最后处理的 slot 重新订阅，该 slot 内已处理的交易由 delivered 去重
+            resume_slot = delivered.last_slot().or(resume_slot);
+
+            attempt += 1;
+            if !self.reconnect_policy.should_retry(attempt) {
+                return Err(ClientError::RetriesExhausted {
+                    attempts: attempt - 1,
+                    last: Box::new(error),
+                });
+            }
+            let delay = self.reconnect_policy.backoff(attempt);
+            self.emit(SubscriptionEvent::Reconnecting { attempt, delay });
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = self.shutdown.cancelled() => return Ok(()),
             }
         }
+    }
+
+    // 读流和处理分开运行：读流端只负责入队，处理在单独的任务中进行，
+    // 队列满时按 PipelineConfig 的 overflow 策略处理
+    pub async fn subscribe_pipelined<F, Fut>(
+        &self,
+        handle: SubscriptionHandle,
+        mut on_update: F,
+    ) -> Result<(), ClientError>
+    where
+        F: FnMut(SubscribeUpdate) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), ClientError>> + Send,
+    {
+        let (tx, rx) = pipeline::bounded(self.pipeline.clone(), self.queue_metrics.clone())
+            .map_err(|e| ClientError::Config(format!("failed to create pipeline: {}", e)))?;
+        // 处理任务处理完才算完成，检查点在这里记录而不是入队时
+        let checkpoint = self.checkpoint.clone();
+        let processor = tokio::spawn(async move {
+            while let Some(update) = rx.recv().await {
+                let slot = update.update_oneof.as_ref().and_then(update_slot);
+                match on_update(update).await {
+                    Ok(()) => {
+                        if let (Some(checkpoint), Some(slot)) = (&checkpoint, slot) {
+                            checkpoint.record(slot);
+                        }
+                    }
+                    Err(e) => error!("Error handling update: {:?}", e),
+                }
+            }
+        });
 
-        Ok(())
+        let result = self
+            .supervise(handle, move |update| {
+                let tx = tx.clone();
+                async move { tx.send(update).await }
+            })
+            .await;
+
+        // 读流结束后发送端已关闭，等待队列中剩余的数据处理完
+        if let Err(e) = processor.await {
+            error!("Pipeline processor stopped: {:?}", e);
+        }
+        self.flush_checkpoint();
+        result
+    }
+
+    // 请求里指定了 from_slot 时优先使用；否则从检查点继续，并且不早于服务端还能回放的最早 slot
+    async fn initial_slot(&self, handle: &SubscriptionHandle) -> Option<u64> {
+        if let Some(from_slot) = handle.current_request().from_slot {
+            return Some(from_slot);
+        }
+        let checkpoint = self.checkpoint.as_ref()?;
+        let slot = match checkpoint.load() {
+            Ok(slot) => slot?,
+            Err(e) => {
+                warn!("Failed to load checkpoint {}: {}", checkpoint.name(), e);
+                return None;
+            }
+        };
+
+        let first_available = match self.rpc().await {
+            Ok(mut rpc) => rpc.first_available_slot().await,
+            Err(e) => Err(e),
+        };
+        match first_available {
+            Ok(Some(first)) if first > slot => {
+                warn!(
+                    "Checkpoint slot {} is no longer available, resuming from {}",
+                    slot, first
+                );
+                self.emit(SubscriptionEvent::Gap {
+                    from_slot: slot,
+                    to_slot: Some(first - 1),
+                });
+                Some(first)
+            }
+            Ok(Some(_)) => {
+                info!(
+                    "Resuming {} from checkpoint slot {}",
+                    checkpoint.name(),
+                    slot
+                );
+                Some(slot)
+            }
+            Ok(None) => {
+                warn!("Replay is not supported, checkpoint slot {} ignored", slot);
+                self.emit(SubscriptionEvent::Gap {
+                    from_slot: slot,
+                    to_slot: None,
+                });
+                None
+            }
+            // 查询失败时仍然尝试回放，被拒绝后按回放不可用处理
+            Err(e) => {
+                warn!("Failed to get first available slot: {}", e);
+                Some(slot)
+            }
+        }
+    }
+
+    fn flush_checkpoint(&self) {
+        if let Some(checkpoint) = &self.checkpoint
+            && let Err(e) = checkpoint.flush()
+        {
+            error!("Failed to save checkpoint {}: {}", checkpoint.name(), e);
+        }
     }
 
-    pub async fn subscribe_account(
+    async fn run_stream<F, Fut>(
         &self,
-        program_id: String,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let client = GeyserGrpcClient::build_from_shared(self.endpoint.clone())?
-            .tls_config(ClientTlsConfig::new().with_native_roots())?
-            .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT))
-            .keep_alive_while_idle(true)
-            .timeout(Duration::from_secs(KEEP_ALIVE_TIMEOUT))
-            .connect()
-            .await?;
-
-        let client = Arc::new(Mutex::new(client));
-
-        let subscribe_request = SubscribeRequest {
-            transactions: HashMap::from([(
-                "".to_string(),
-                SubscribeRequestFilterTransactions {
-                    vote: Some(false),
-                    failed: Some(false),
-                    signature: None,
-                    account_include: vec![
-                        "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P".to_string(),
-                    ],
-                    account_exclude: vec![],
-                    account_required: vec![],
-                },
-            )]),
-            commitment: Some(CommitmentLevel::Processed.into()),
-            ..Default::default()
+        handle: &SubscriptionHandle,
+        resume_from: Option<u64>,
+        on_update: &mut F,
+        delivered: &mut ReplayDedup,
+        gap_detector: &mut Option<SlotGapDetector>,
+        attempt: u32,
+    ) -> StreamOutcome
+    where
+        F: FnMut(SubscribeUpdate) -> Fut,
+        Fut: Future<Output = Result<(), ClientError>>,
+    {
+        // 先订阅变更再读取请求，避免漏掉两者之间的修改
+        let mut request_rx = handle.watch();
+        let mut request = self.prepare_request(request_rx.borrow_and_update().clone());
+        request.from_slot = resume_from;
+        let commitment = request
+            .commitment
+            .and_then(|c| CommitmentLevel::try_from(c).ok());
+
+        if self.shutdown.is_cancelled() {
+            return StreamOutcome::Shutdown;
+        }
+        let mut client = match self.connect().await {
+            Ok(client) => client,
+            Err(error) => {
+                return StreamOutcome::Disconnected {
+                    error,
+                    received: false,
+                };
+            }
         };
 
-        // let version = client.lock().await.get_version().await.unwrap();
-        // println!("{:?}", version);
+        let (mut subscribe_tx, mut stream) =
+            match client.subscribe_with_request(Some(request)).await {
+                Ok(res) => res,
+                Err(e) => {
+                    return StreamOutcome::Disconnected {
+                        error: e.into(),
+                        received: false,
+                    };
+                }
+            };
+        self.emit(SubscriptionEvent::Connected {
+            endpoint: self.endpoint.clone(),
+            attempt,
+            from_slot: resume_from,
+        });
+
+        // 记录重连时的链上最新 slot，用于上报回放区间
+        let tip = match resume_from {
+            Some(_) => client.get_slot(commitment).await.ok().map(|res| res.slot),
+            None => None,
+        };
 
-        let (mut subscribe_tx, mut stream) = client
-            .lock()
-            .await
-            .subscribe_with_request(Some(subscribe_request))
-            .await?;
+        let mut received = false;
+        let mut pings = PingTracker::new();
+        let mut last_activity = Instant::now();
+        let mut ping_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + self.watchdog.ping_interval,
+            self.watchdog.ping_interval,
+        );
+        ping_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let stale_deadline =
+                tokio::time::Instant::from_std(last_activity + self.watchdog.stale_timeout);
+            let message = tokio::select! {
+                // 当前正在处理的更新会先处理完
+                _ = self.shutdown.cancelled() => return StreamOutcome::Shutdown,
+                message = stream.next() => message,
+                _ = ping_interval.tick() => {
+                    self.send_ping(&mut subscribe_tx, &mut pings).await;
+                    continue;
+                }
+                _ = tokio::time::sleep_until(stale_deadline) => {
+                    let idle = last_activity.elapsed();
+                    self.emit(SubscriptionEvent::Stalled { idle });
+                    return StreamOutcome::Disconnected {
+                        error: ClientError::Disconnected(format!("no update received for {:?}", idle)),
+                        received,
+                    };
+                }
+                Ok(()) = request_rx.changed() => {
+                    // 在当前连接上替换过滤条件，不需要重连
+                    let mut request = self.prepare_request(request_rx.borrow_and_update().clone());
+                    request.from_slot = None;
+                    request.ping = None;
+                    match subscribe_tx.send(request).await {
+                        Ok(()) => info!("Subscription filters updated"),
+                        Err(e) => error!("Failed to update subscription: {:?}", e),
+                    }
+                    continue;
+                }
+            };
+            let Some(message) = message else {
+                break;
+            };
 
-        while let Some(message) = stream.next().await {
-            // info!("{:?}", message);
             match message {
-                Ok(msg) => match msg.update_oneof {
-                    // Some(UpdateOneof::Account(account)) => {
-                    //     info!("{:?}", account);
-                    // }
-                    Some(UpdateOneof::Transaction(ts)) => {
-                        // info!("{:?}", ts);
-                        if let Some(sut) = ts.transaction {
-                            if let Some(mate) = sut.meta {
-                                // info!("{:?}", mate);
-                                // info!("pre_balances: {:?} \n {:?}", mate.pre_balances, mate.post_balances);
-                                // info!("[prost_balances: {:?}, post_balances{:?}", mate.post_balances, mate.post_token_balances);
-                                info!("len: {}", mate.post_token_balances.len());
-                                zip(mate.pre_token_balances, mate.post_token_balances)
-                                    .into_iter()
-                                    .for_each(|(a, b)| {
-                                        info!(
-                                            "{}, {}, {}, {}",
-                                            a.mint,
-                                            a.ui_token_amount.unwrap().ui_amount_string,
-                                            b.mint,
-                                            b.ui_token_amount.unwrap().ui_amount_string
-                                        );
-                                    });
+                Ok(msg) => {
+                    last_activity = Instant::now();
+                    if !received {
+                        received = true;
+                        if let Some(from_slot) = resume_from {
+                            self.emit(SubscriptionEvent::Recovered {
+                                from_slot,
+                                to_slot: tip,
+                            });
+                        }
+                    }
+
+                    match msg.update_oneof {
+                        Some(UpdateOneof::Ping(_)) => {
+                            // 回应服务端的 ping，保持连接
+                            self.send_ping(&mut subscribe_tx, &mut pings).await;
+                            continue;
+                        }
+                        Some(UpdateOneof::Pong(pong)) => {
+                            if let Some(rtt) = pings.on_pong(pong.id, last_activity) {
+                                debug!("Pong {} rtt: {:?}", pong.id, rtt);
+                                self.metrics.record_rtt(rtt);
                             }
+                            continue;
                         }
+                        _ => {}
+                    }
+
+                    if let Some(created_at) = &msg.created_at {
+                        self.latency
+                            .record_receive(&msg.filters, created_at, SystemTime::now());
+                    }
+
+                    let backfill = match (&msg.update_oneof, gap_detector.as_mut()) {
+                        (Some(UpdateOneof::Slot(update)), Some(detector)) => {
+                            self.check_slot_gaps(detector, update)
+                        }
+                        _ => None,
+                    };
+
+                    // 只匹配缺口检测过滤器的 slot 更新不交给业务回调
+                    let internal = !msg.filters.is_empty()
+                        && msg
+                            .filters
+                            .iter()
+                            .all(|filter| filter == GAP_DETECTOR_FILTER);
+                    let replayed = msg
+                        .update_oneof
+                        .as_ref()
+                        .is_some_and(|update| !delivered.observe(update));
+                    if replayed {
+                        debug!("Skipping replayed update");
+                    } else if !internal {
+                        let slot = msg.update_oneof.as_ref().and_then(update_slot);
+                        if let Err(e) = on_update(msg).await {
+                            error!("Error handling update: {:?}", e);
+                        }
+                        if let Some(slot) = slot {
+                            self.metrics.set_last_slot(slot);
+                        }
+                    }
+
+                    if let Some((from_slot, to_slot)) = backfill {
+                        return StreamOutcome::Backfill { from_slot, to_slot };
                     }
-                    _ => {} // Some(UpdateOneof::Ping(ping)) => {
-                            //     info!("{:?}", ping);
-                            // }
-                            // x => {
-                            //     info!("{:?}", x)
-                            // }
-                },
-                Err(e) => {
-                    error!("Error: {:?}", e);
-                    break;
+                }
+                Err(status) => {
+                    if !received
+                        && resume_from.is_some()
+                        && matches!(
+                            status.code(),
+                            Code::InvalidArgument | Code::OutOfRange | Code::FailedPrecondition
+                        )
+                    {
+                        return StreamOutcome::ReplayUnavailable {
+                            reason: status.to_string(),
+                            to_slot: tip,
+                        };
+                    }
+                    return StreamOutcome::Disconnected {
+                        error: status.into(),
+                        received,
+                    };
                 }
             }
         }
 
-        Ok(())
+        StreamOutcome::Disconnected {
+            error: ClientError::Disconnected("stream closed by server".to_string()),
+            received,
+        }
     }
 
-    pub async fn subscribe_price(
-        &self,
-        // program_id: String,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let client = GeyserGrpcClient::build_from_shared(self.endpoint.clone())?
-            .tls_config(ClientTlsConfig::new().with_native_roots())?
-            .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT))
-            .keep_alive_while_idle(true)
-            .timeout(Duration::from_secs(KEEP_ALIVE_TIMEOUT))
-            .connect()
-            .await?;
+    fn prepare_request(&self, mut request: SubscribeRequest) -> SubscribeRequest {
+        if let Some(config) = &self.gap_detection {
+            config.apply(&mut request);
+        }
+        if self.lifecycle.is_some() {
+            CommitmentTracker::apply(&mut request);
+        }
+        request
+    }
 
-        let client = Arc::new(Mutex::new(client));
-        let subscribe_request = request_sub_wallet();
+    // 上报缺口，返回需要回填的区间
+    fn check_slot_gaps(
+        &self,
+        detector: &mut SlotGapDetector,
+        update: &SubscribeUpdateSlot,
+    ) -> Option<(u64, u64)> {
+        let config = self.gap_detection.as_ref()?;
+        let mut backfill: Option<(u64, u64)> = None;
+        for gap in detector.observe(update) {
+            if config.backfill
+                && gap.kind == GapKind::Dropped
+                && gap.slot_count() <= config.max_backfill_slots
+            {
+                backfill = Some(match backfill {
+                    Some((from_slot, _)) => (from_slot, gap.to_slot),
+                    None => (gap.from_slot, gap.to_slot),
+                });
+            }
+            self.emit(SubscriptionEvent::SlotGap(gap));
+        }
+        backfill
+    }
 
-        let (mut subscribe_tx, mut stream) = client
-            .lock()
+    // 通用订阅入口：重连、存活检测、队列都由这里处理，新的订阅类型只需提供 dispatcher
+    pub async fn run<D>(
+        &self,
+        request: SubscribeRequest,
+        dispatcher: Arc<Mutex<D>>,
+    ) -> Result<(), ClientError>
+    where
+        D: UpdateDispatcher + 'static,
+    {
+        self.run_with_handle(SubscriptionHandle::new(request), dispatcher)
             .await
-            .subscribe_with_request(Some(subscribe_request))
-            .await?;
+    }
 
-        while let Some(message) = stream.next().await {
-            if message.is_err() {
-                error!("Error!");
-            }
-            let message = message.unwrap();
-            info!("---------------------");
-            // info!("{}", message.)
-            info!("{:?}", message.filters);
-            info!("时间戳： {:?}", message.created_at);
-            match message.update_oneof {
-                Some(UpdateOneof::Account(account)) => info!("account: {:?}", account),
-                Some(UpdateOneof::Block(block)) => info!("block: {:?}", block),
-                Some(UpdateOneof::Entry(entry)) => info!("entry: {:?}", entry),
-                Some(UpdateOneof::Slot(slot)) => info!("slot: {:?}", slot),
-                Some(UpdateOneof::Transaction(tx)) => info!("subscribeupdateTransaction: {:?}", tx), // 信息最多
-
-                Some(UpdateOneof::Ping(_)) => {
-                    let _ = subscribe_tx.send(SubscribeRequest {
-                        ping: Some(SubscribeRequestPing { id: 1 }),
-                        ..Default::default()
-                    }).await;
-                }
-                _ => {},
-            }
-        
-            info!("---------------------\n");
-
-            // match message {
-            //     Ok(msg) => match msg.update_oneof {
-            //         Some(UpdateOneof::Account(account)) => {
-            //             info!("{:?}", account);
-            //         }
-            //         a => {info!("{:?}", a)}
-            //     },
-            //     Err(e) => {
-            //         error!("Error: {:?}", e);
-            //         break;
-            //     }
-            // }
+    pub async fn run_with_handle<D>(
+        &self,
+        handle: SubscriptionHandle,
+        dispatcher: Arc<Mutex<D>>,
+    ) -> Result<(), ClientError>
+    where
+        D: UpdateDispatcher + 'static,
+    {
+        let processor = dispatcher.clone();
+        let result = self
+            .subscribe_pipelined(handle, move |update| {
+                let dispatcher = processor.clone();
+                async move { dispatcher.lock().await.on_update(update).await }
+            })
+            .await;
+
+        // 队列已经处理完，最后 flush 一次
+        if let Err(e) = dispatcher.lock().await.flush().await {
+            error!("Failed to flush dispatcher: {:?}", e);
         }
+        result
+    }
 
-        Ok(())
+    pub async fn subscribe(&self, program_id: String) -> Result<(), ClientError> {
+        let request = transactions_request("client", &[program_id], CommitmentLevel::Processed)?;
+        self.run(request, self.event_handler.clone()).await
     }
-}
 
-fn request_sub_wallet() -> SubscribeRequest {
-    SubscribeRequest {
-        // accounts: HashMap::from([(
-        //     "price".to_string(),
-        //     SubscribeRequestFilterAccounts {
-
-        //         // account: vec!["8sLbNZoA1cfnvMJLPfp98ZLAnFSYCFApfJKMbiXNLwxj".to_string()],
-        //         // owner: vec!["CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK".to_string()],
-        //         ..Default::default()
-        //     },
-        // )]),
-        transactions: HashMap::from([(
-            "wellet".to_string(),
-            SubscribeRequestFilterTransactions {
-                vote: Some(false),
-                failed: Some(false),
-                signature: None,
-                account_include: vec!["5EeGiP8pt14DXY52atNTPnfwP7kxdREHQdgo1NRS4tbq".to_string()],
-                ..Default::default()
-            },
-        )]),
-        accounts_data_slice: vec![SubscribeRequestAccountsDataSlice {
-            offset: 253,
-            length: 16,
-        }],
-        commitment: Some(CommitmentLevel::Processed.into()),
-        ..Default::default()
+    pub async fn subscribe_account(&self, program_id: String) -> Result<(), ClientError> {
+        let request = transactions_request("client", &[program_id], CommitmentLevel::Processed)?;
+        self.run(request, Arc::new(Mutex::new(TokenBalanceLogger)))
+            .await
+    }
+
+    pub async fn subscribe_price(&self, wallet: String) -> Result<(), ClientError> {
+        let request = transactions_request("wallet", &[wallet], CommitmentLevel::Processed)?;
+        self.run(request, Arc::new(Mutex::new(UpdateLogger))).await
     }
 }
 
-// fn request_price() -> SubscribeRequest {
-//     SubscribeRequest {
-//         accounts: HashMap::from([(
-//             "price".to_string(),
-//             SubscribeRequestFilterAccounts {
-//                 account: vec!["8sLbNZoA1cfnvMJLPfp98ZLAnFSYCFApfJKMbiXNLwxj".to_string()],
-//                 owner: vec!["CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK".to_string()],
-//                 ..Default::default()
-//             },
-//         )]),
-//         accounts_data_slice: vec![SubscribeRequestAccountsDataSlice {
-//             offset: 253,
-//             length: 16,
-//         }],
-//         commitment: Some(CommitmentLevel::Processed.into()),
-//         ..Default::default()
-//     }
-// }
+// 取出已处理数据所在的 slot，用于检查点；slot 状态更新可能领先于实际收到的交易，不算在内
+fn update_slot(update: &UpdateOneof) -> Option<u64> {
+    match update {
+        UpdateOneof::Account(account) => Some(account.slot),
+        UpdateOneof::Transaction(tx) => Some(tx.slot),
+        _ => None,
+    }
+}
@@ -1,56 +1,244 @@
-use std::{collections::HashMap, error::Error};
+use std::{collections::HashMap, fmt::Debug, iter::zip, str::FromStr, time::SystemTime};
 
 use log::info;
+use solana_sdk::bs58;
+use yellowstone_grpc_proto::{
+    geyser::{
+        SubscribeUpdateAccount, SubscribeUpdateBlock, SubscribeUpdateBlockMeta,
+        SubscribeUpdateEntry, SubscribeUpdateSlot, SubscribeUpdateTransaction,
+        SubscribeUpdateTransactionStatus,
+    },
+    prost_types::Timestamp,
+};
 
+use crate::engine::{DispatchResult, UpdateContext, UpdateDispatcher};
+use crate::error::ClientError;
+use crate::latency::{LatencyTracker, latency_since};
+use crate::lifecycle::CommitmentTracker;
 use crate::model::{
-    EventTrait,
+    EventTrait, LogEvent,
+    invocation::InvocationTree,
     pumpamm::{BuyEvent, CreatePoolEvent, SellEvent},
     pumpfun_model::{CompleteEvent, CreateEvent, TradeEvent},
 };
 
-#[derive(Clone)]
+pub const PUMP_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+pub const PUMP_AMM_PROGRAM_ID: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
+
+// 可以按程序单独启用的事件解析器
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Decoder {
+    Pump,
+    PumpAmm,
+}
+
+impl Decoder {
+    pub const ALL: [Decoder; 2] = [Decoder::Pump, Decoder::PumpAmm];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Decoder::Pump => "pump",
+            Decoder::PumpAmm => "pump_amm",
+        }
+    }
+
+    // 默认只解析这个程序的日志
+    pub fn program_id(self) -> &'static str {
+        match self {
+            Decoder::Pump => PUMP_PROGRAM_ID,
+            Decoder::PumpAmm => PUMP_AMM_PROGRAM_ID,
+        }
+    }
+
+    pub fn event_types(self) -> &'static [&'static str] {
+        match self {
+            Decoder::Pump => &[CreateEvent::NAME, CompleteEvent::NAME, TradeEvent::NAME],
+            Decoder::PumpAmm => &[BuyEvent::NAME, SellEvent::NAME, CreatePoolEvent::NAME],
+        }
+    }
+}
+
+impl FromStr for Decoder {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Decoder::ALL
+            .into_iter()
+            .find(|decoder| decoder.name() == s)
+            .ok_or_else(|| ClientError::Config(format!("unknown decoder {}", s)))
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct EventHandler {
-    events: HashMap<String, Vec<(u64, String)>>,
+    // 按签名保存交易所在的 slot 和解析出的事件
+    events: HashMap<String, Vec<(u64, TxEvent)>>,
+    // 为 None 时对所有交易运行全部解析器；否则只在调用了对应程序的交易上运行，
+    // 配置了但为空时不解析事件
+    decoders: Option<Vec<(Decoder, Vec<String>)>>,
+    // 开启后跟踪已上报事件所在 slot 的确认状态
+    lifecycle: Option<CommitmentTracker>,
+    // 按事件类型统计 created_at 到解析完成的延迟
+    latency: Option<LatencyTracker>,
 }
 
 #[derive(Debug)]
 pub struct PumpEvents {
-    pub create: Option<CreateEvent>,
+    pub create: Vec<LogEvent<CreateEvent>>,
 
-    pub complete: Option<CompleteEvent>,
+    pub complete: Vec<LogEvent<CompleteEvent>>,
 
-    pub trade: Option<TradeEvent>,
+    pub trade: Vec<LogEvent<TradeEvent>>,
 }
 
 #[derive(Debug)]
 pub struct PumpAmmEvents {
-    pub buy: Option<BuyEvent>,
-    pub sell: Option<SellEvent>,
-    pub create_pool: Option<CreatePoolEvent>,
+    pub buy: Vec<LogEvent<BuyEvent>>,
+    pub sell: Vec<LogEvent<SellEvent>>,
+    pub create_pool: Vec<LogEvent<CreatePoolEvent>>,
+}
+
+// 交易中解析出的一个事件
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxEvent {
+    pub log_index: usize,
+    // 调用深度，1 为顶层指令
+    pub depth: usize,
+    // 通过 CPI 调用时的上层程序
+    pub parent: Option<String>,
+    pub event_type: &'static str,
+    pub event: String,
+}
+
+impl TxEvent {
+    fn new<T: EventTrait>(log_event: LogEvent<T>) -> Self {
+        Self {
+            log_index: log_event.log_index,
+            depth: log_event.depth,
+            parent: log_event.parent,
+            event_type: T::NAME,
+            event: format!("{:?}", log_event.event),
+        }
+    }
 }
 
 impl EventHandler {
     pub fn new() -> Self {
-        Self {
-            events: HashMap::new(),
+        Self::default()
+    }
+
+    pub fn with_lifecycle(mut self, tracker: CommitmentTracker) -> Self {
+        self.lifecycle = Some(tracker);
+        self
+    }
+
+    pub fn lifecycle(&self) -> Option<&CommitmentTracker> {
+        self.lifecycle.as_ref()
+    }
+
+    pub fn with_latency(mut self, latency: LatencyTracker) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    // programs 为空时只解析解析器对应程序输出的事件
+    pub fn with_decoder<I, S>(mut self, decoder: Decoder, programs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.decoders
+            .get_or_insert_default()
+            .push((decoder, programs.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    // 只运行这些解析器，为空时不解析任何事件
+    pub fn with_decoders<I, S>(mut self, decoders: I) -> Self
+    where
+        I: IntoIterator<Item = (Decoder, Vec<S>)>,
+        S: Into<String>,
+    {
+        self.decoders.get_or_insert_default().extend(
+            decoders.into_iter().map(|(decoder, programs)| {
+                (decoder, programs.into_iter().map(Into::into).collect())
+            }),
+        );
+        self
+    }
+
+    // 根据 slot 状态上报事件的 Confirmed / Finalized / RolledBack
+    pub fn handle_slot(&mut self, update: &SubscribeUpdateSlot) {
+        let Some(tracker) = self.lifecycle.as_mut() else {
+            return;
+        };
+        for notification in tracker.on_slot(update) {
+            info!(
+                "tx: {} {:?} (slot {})",
+                notification.signature, notification.stage, notification.slot
+            );
         }
     }
 
+    // 某笔交易已解析出的事件
+    pub fn events(&self, signature: &str) -> &[(u64, TxEvent)] {
+        self.events
+            .get(signature)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
     pub fn parse_pump_events(&self, logs: &[String]) -> PumpEvents {
-        PumpEvents {
-            create: CreateEvent::parse_logs::<CreateEvent>(logs),
-            complete: CreateEvent::parse_logs::<CompleteEvent>(logs),
-            trade: CreateEvent::parse_logs::<TradeEvent>(logs),
-        }
+        pump_events(logs, &InvocationTree::parse(logs), &[PUMP_PROGRAM_ID])
     }
 
     // 添加更多池子交易解析 Pump AMM
     pub fn parse_pump_amm_events(&self, logs: &[String]) -> PumpAmmEvents {
-        PumpAmmEvents {
-            buy: BuyEvent::parse_logs::<BuyEvent>(logs),
-            sell: SellEvent::parse_logs::<SellEvent>(logs),
-            create_pool: CreatePoolEvent::parse_logs::<CreatePoolEvent>(logs),
+        pump_amm_events(logs, &InvocationTree::parse(logs), &[PUMP_AMM_PROGRAM_ID])
+    }
+
+    // 解析日志中的所有已知事件，按日志顺序返回。
+    // 每个解析器只接受配置的程序（默认是解析器对应的程序）输出的事件
+    pub fn decode_logs(&self, logs: &[String]) -> Vec<TxEvent> {
+        let tree = InvocationTree::parse(logs);
+        let mut tx_events = Vec::new();
+        match &self.decoders {
+            None => {
+                for decoder in Decoder::ALL {
+                    decode_with(
+                        decoder,
+                        logs,
+                        &tree,
+                        &[decoder.program_id()],
+                        &mut tx_events,
+                    );
+                }
+            }
+            Some(decoders) => {
+                for (decoder, programs) in decoders {
+                    if programs.is_empty() {
+                        decode_with(
+                            *decoder,
+                            logs,
+                            &tree,
+                            &[decoder.program_id()],
+                            &mut tx_events,
+                        );
+                    } else {
+                        decode_with(*decoder, logs, &tree, programs, &mut tx_events);
+                    }
+                }
+            }
         }
+        tx_events.sort_by_key(|event| event.log_index);
+        tx_events
+    }
+
+    pub async fn handle_transaction(
+        &mut self,
+        sut: SubscribeUpdateTransaction,
+    ) -> Result<(), ClientError> {
+        self.process_transaction(sut, None).await
     }
 
     pub async fn handle_logs(
@@ -58,42 +246,54 @@ impl EventHandler {
         logs: &[String],
         slot: u64,
         signature: String,
-    ) -> Result<(), Box<dyn Error>> {
-        let mut tx_events = Vec::new();
+    ) -> Result<(), ClientError> {
+        self.process_logs(logs, slot, signature, None).await
+    }
 
-        // 解析 Pump 事件
-        let pump_events = self.parse_pump_events(logs);
-        if let Some(create) = pump_events.create {
-            tx_events.push(format!("{:?}", create));
-        }
-        if let Some(complete) = pump_events.complete {
-            tx_events.push(format!("{:?}", complete));
-        }
-        if let Some(trade) = pump_events.trade {
-            tx_events.push(format!("{:?}", trade));
+    async fn process_transaction(
+        &mut self,
+        sut: SubscribeUpdateTransaction,
+        created_at: Option<&Timestamp>,
+    ) -> Result<(), ClientError> {
+        let Some(info) = sut.transaction else {
+            return Ok(());
+        };
+        let logs = info.meta.map(|meta| meta.log_messages).unwrap_or_default();
+        if logs.is_empty() {
+            return Ok(());
         }
 
-        // 解析 PumpAmm 事件
-        let pump_amm_events = self.parse_pump_amm_events(logs);
-        if let Some(buy) = pump_amm_events.buy {
-            tx_events.push(format!("{:?}", buy));
-        }
-        if let Some(sell) = pump_amm_events.sell {
-            tx_events.push(format!("{:?}", sell));
-        }
-        if let Some(create_pool) = pump_amm_events.create_pool {
-            tx_events.push(format!("{:?}", create_pool));
+        let signature = if info.signature.is_empty() {
+            "unknown".to_string()
+        } else {
+            bs58::encode(&info.signature).into_string()
+        };
+        self.process_logs(&logs, sut.slot, signature, created_at)
+            .await
+    }
+
+    async fn process_logs(
+        &mut self,
+        logs: &[String],
+        slot: u64,
+        signature: String,
+        created_at: Option<&Timestamp>,
+    ) -> Result<(), ClientError> {
+        let tx_events = self.decode_logs(logs);
+
+        if let (Some(latency), Some(created_at)) = (&self.latency, created_at) {
+            let decoded_at = SystemTime::now();
+            for tx_event in &tx_events {
+                latency.record_decode(tx_event.event_type, created_at, decoded_at);
+            }
         }
 
         if !tx_events.is_empty() {
             // 将事件添加到 HashMap
-            for event_data in tx_events {
-                let event_info = (slot, event_data);
-                self.events
-                    .entry(signature.clone())
-                    .or_insert_with(Vec::new)
-                    .push(event_info);
-            }
+            self.events
+                .entry(signature.clone())
+                .or_default()
+                .extend(tx_events.into_iter().map(|tx_event| (slot, tx_event)));
 
             // 打印当前交易的所有事件
             if let Some(events) = self.events.get(&signature) {
@@ -101,12 +301,282 @@ impl EventHandler {
                 info!("slot: {}", slot);
                 info!("tx: {}", signature);
                 info!("events:");
-                for (_, event_data) in events {
-                    info!("  - {}", event_data);
+                for (_, tx_event) in events {
+                    match &tx_event.parent {
+                        Some(parent) => info!(
+                            "  - #{} {} (depth {}, via {})",
+                            tx_event.log_index, tx_event.event, tx_event.depth, parent
+                        ),
+                        None => info!(
+                            "  - #{} {} (depth {})",
+                            tx_event.log_index, tx_event.event, tx_event.depth
+                        ),
+                    }
                 }
                 info!("-----------------------------------------------");
             }
+
+            if let Some(tracker) = self.lifecycle.as_mut() {
+                tracker.track(slot, signature);
+            }
         }
         Ok(())
     }
 }
+
+impl UpdateDispatcher for EventHandler {
+    async fn on_slot(&mut self, _ctx: &UpdateContext, slot: SubscribeUpdateSlot) -> DispatchResult {
+        self.handle_slot(&slot);
+        Ok(())
+    }
+
+    async fn on_transaction(
+        &mut self,
+        ctx: &UpdateContext,
+        transaction: SubscribeUpdateTransaction,
+    ) -> DispatchResult {
+        self.process_transaction(transaction, ctx.created_at.as_ref())
+            .await
+    }
+
+    async fn flush(&mut self) -> DispatchResult {
+        let pending = self
+            .lifecycle
+            .as_ref()
+            .map_or(0, CommitmentTracker::pending);
+        info!(
+            "Event handler stopped: {} transactions with events, {} not finalized",
+            self.events.len(),
+            pending
+        );
+        Ok(())
+    }
+}
+
+fn pump_events<S: AsRef<str>>(
+    logs: &[String],
+    tree: &InvocationTree,
+    programs: &[S],
+) -> PumpEvents {
+    PumpEvents {
+        create: CreateEvent::parse_logs_from(logs, tree, programs),
+        complete: CompleteEvent::parse_logs_from(logs, tree, programs),
+        trade: TradeEvent::parse_logs_from(logs, tree, programs),
+    }
+}
+
+fn pump_amm_events<S: AsRef<str>>(
+    logs: &[String],
+    tree: &InvocationTree,
+    programs: &[S],
+) -> PumpAmmEvents {
+    PumpAmmEvents {
+        buy: BuyEvent::parse_logs_from(logs, tree, programs),
+        sell: SellEvent::parse_logs_from(logs, tree, programs),
+        create_pool: CreatePoolEvent::parse_logs_from(logs, tree, programs),
+    }
+}
+
+fn decode_with<S: AsRef<str>>(
+    decoder: Decoder,
+    logs: &[String],
+    tree: &InvocationTree,
+    programs: &[S],
+    tx_events: &mut Vec<TxEvent>,
+) {
+    // 配置的程序都没有被调用时跳过
+    if !tree.invocations().iter().any(|invocation| {
+        programs
+            .iter()
+            .any(|program| program.as_ref() == invocation.program)
+    }) {
+        return;
+    }
+    match decoder {
+        // 解析 Pump 事件
+        Decoder::Pump => {
+            let pump_events = pump_events(logs, tree, programs);
+            tx_events.extend(pump_events.create.into_iter().map(TxEvent::new));
+            tx_events.extend(pump_events.complete.into_iter().map(TxEvent::new));
+            tx_events.extend(pump_events.trade.into_iter().map(TxEvent::new));
+        }
+        // 解析 PumpAmm 事件
+        Decoder::PumpAmm => {
+            let pump_amm_events = pump_amm_events(logs, tree, programs);
+            tx_events.extend(pump_amm_events.buy.into_iter().map(TxEvent::new));
+            tx_events.extend(pump_amm_events.sell.into_iter().map(TxEvent::new));
+            tx_events.extend(pump_amm_events.create_pool.into_iter().map(TxEvent::new));
+        }
+    }
+}
+
+// 打印交易前后的代币余额
+pub struct TokenBalanceLogger;
+
+impl UpdateDispatcher for TokenBalanceLogger {
+    async fn on_transaction(
+        &mut self,
+        _ctx: &UpdateContext,
+        transaction: SubscribeUpdateTransaction,
+    ) -> DispatchResult {
+        let Some(meta) = transaction.transaction.and_then(|info| info.meta) else {
+            return Ok(());
+        };
+        info!("len: {}", meta.post_token_balances.len());
+        zip(meta.pre_token_balances, meta.post_token_balances).for_each(|(a, b)| {
+            info!(
+                "{}, {}, {}, {}",
+                a.mint,
+                a.ui_token_amount
+                    .map(|amount| amount.ui_amount_string)
+                    .unwrap_or_default(),
+                b.mint,
+                b.ui_token_amount
+                    .map(|amount| amount.ui_amount_string)
+                    .unwrap_or_default()
+            );
+        });
+        Ok(())
+    }
+}
+
+// 打印收到的所有更新
+pub struct UpdateLogger;
+
+impl UpdateLogger {
+    fn log(ctx: &UpdateContext, kind: &str, update: &dyn Debug) -> DispatchResult {
+        info!("---------------------");
+        info!("{:?}", ctx.filters);
+        match &ctx.created_at {
+            Some(created_at) => info!(
+                "时间戳： {:?}，延迟：{:?}",
+                created_at,
+                latency_since(created_at, SystemTime::now())
+            ),
+            None => info!("时间戳： None"),
+        }
+        info!("{}: {:?}", kind, update);
+        info!("---------------------\n");
+        Ok(())
+    }
+}
+
+impl UpdateDispatcher for UpdateLogger {
+    async fn on_account(
+        &mut self,
+        ctx: &UpdateContext,
+        account: SubscribeUpdateAccount,
+    ) -> DispatchResult {
+        Self::log(ctx, "account", &account)
+    }
+
+    async fn on_slot(&mut self, ctx: &UpdateContext, slot: SubscribeUpdateSlot) -> DispatchResult {
+        Self::log(ctx, "slot", &slot)
+    }
+
+    // 信息最多
+    async fn on_transaction(
+        &mut self,
+        ctx: &UpdateContext,
+        transaction: SubscribeUpdateTransaction,
+    ) -> DispatchResult {
+        Self::log(ctx, "subscribeupdateTransaction", &transaction)
+    }
+
+    async fn on_transaction_status(
+        &mut self,
+        ctx: &UpdateContext,
+        status: SubscribeUpdateTransactionStatus,
+    ) -> DispatchResult {
+        Self::log(ctx, "transaction status", &status)
+    }
+
+    async fn on_block(
+        &mut self,
+        ctx: &UpdateContext,
+        block: SubscribeUpdateBlock,
+    ) -> DispatchResult {
+        Self::log(ctx, "block", &block)
+    }
+
+    async fn on_block_meta(
+        &mut self,
+        ctx: &UpdateContext,
+        block_meta: SubscribeUpdateBlockMeta,
+    ) -> DispatchResult {
+        Self::log(ctx, "block meta", &block_meta)
+    }
+
+    async fn on_entry(
+        &mut self,
+        ctx: &UpdateContext,
+        entry: SubscribeUpdateEntry,
+    ) -> DispatchResult {
+        Self::log(ctx, "entry", &entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program_data<T: EventTrait + borsh::BorshSerialize>(event: &T) -> String {
+        let mut bytes = T::DISCRIMINATOR.to_vec();
+        event.serialize(&mut bytes).unwrap();
+        format!(
+            "Program data: {}",
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes)
+        )
+    }
+
+    #[test]
+    fn test_decode_all_events_in_log_order() {
+        let invoke = |program: &str| format!("Program {} invoke [1]", program);
+        let success = |program: &str| format!("Program {} success", program);
+        let logs = vec![
+            invoke(PUMP_PROGRAM_ID),
+            program_data(&TradeEvent::default()),
+            success(PUMP_PROGRAM_ID),
+            invoke(PUMP_AMM_PROGRAM_ID),
+            program_data(&BuyEvent::default()),
+            success(PUMP_AMM_PROGRAM_ID),
+            invoke(PUMP_PROGRAM_ID),
+            program_data(&CreateEvent::default()),
+            program_data(&TradeEvent::default()),
+            success(PUMP_PROGRAM_ID),
+        ];
+        let events: Vec<_> = EventHandler::new()
+            .decode_logs(&logs)
+            .into_iter()
+            .map(|event| (event.log_index, event.event_type))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (1, TradeEvent::NAME),
+                (4, BuyEvent::NAME),
+                (7, CreateEvent::NAME),
+                (8, TradeEvent::NAME),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decoder_for_configured_program() {
+        // 例如 devnet 或 fork 部署的 Pump
+        let fork = "FoRkPumpProgram1111111111111111111111111111";
+        let logs = vec![
+            format!("Program {} invoke [1]", fork),
+            program_data(&TradeEvent::default()),
+            format!("Program {} success", fork),
+        ];
+        assert!(EventHandler::new().decode_logs(&logs).is_empty());
+
+        let events = EventHandler::new()
+            .with_decoder(Decoder::Pump, [fork])
+            .decode_logs(&logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, TradeEvent::NAME);
+        assert_eq!(events[0].depth, 1);
+    }
+}
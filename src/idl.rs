@@ -0,0 +1,679 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use base64::{Engine, engine::general_purpose};
+use serde_json::{Map, Value};
+use solana_sdk::{bs58, hash::hash, pubkey::Pubkey};
+
+use crate::{decode::EVENT_IX_TAG, error::ClientError};
+
+const PROGRAM_DATA: &str = "Program data: ";
+// 防止 IDL 中自引用的类型无限递归
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum IdlType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    U128,
+    I128,
+    F32,
+    F64,
+    String,
+    Pubkey,
+    Bytes,
+    Vec(Box<IdlType>),
+    Option(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+    Defined(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Fields {
+    Named(Vec<(String, IdlType)>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TypeDef {
+    Struct(Fields),
+    Enum(Vec<(String, Fields)>),
+}
+
+#[derive(Clone, Debug)]
+struct IdlItem {
+    name: String,
+    discriminator: [u8; 8],
+    fields: Fields,
+    accounts: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedValue {
+    pub name: String,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedIdlInstruction {
+    pub name: String,
+    pub args: Value,
+    // IDL 中的账户名，按指令中的顺序
+    pub accounts: Vec<String>,
+}
+
+// 运行时加载 Anchor IDL，按 discriminator 把事件和指令解析成 JSON。
+// 同时支持旧版（0.29 及以前）和 0.30 之后带 discriminator 的 IDL 格式
+#[derive(Clone, Debug)]
+pub struct IdlDecoder {
+    name: String,
+    address: Option<Pubkey>,
+    events: Vec<IdlItem>,
+    instructions: Vec<IdlItem>,
+    types: HashMap<String, TypeDef>,
+}
+
+impl IdlDecoder {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| {
+            ClientError::Config(format!("failed to read IDL {}: {}", path.display(), e))
+        })?;
+        Self::from_json(&text)
+            .map_err(|e| ClientError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ClientError> {
+        let idl: Value = serde_json::from_str(text)
+            .map_err(|e| ClientError::Config(format!("invalid IDL JSON: {}", e)))?;
+
+        let name = idl
+            .pointer("/metadata/name")
+            .or_else(|| idl.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+        let address = idl
+            .get("address")
+            .or_else(|| idl.pointer("/metadata/address"))
+            .and_then(Value::as_str)
+            .map(|address| {
+                address
+                    .parse::<Pubkey>()
+                    .map_err(|_| idl_error("address", &format!("invalid pubkey {}", address)))
+            })
+            .transpose()?;
+
+        let mut types = HashMap::new();
+        for (i, def) in array(&idl, "types")?.iter().enumerate() {
+            let path = format!("types[{}]", i);
+            let name = str_field(def, "name", &path)?;
+            let ty = def
+                .get("type")
+                .ok_or_else(|| idl_error(&path, "missing type"))?;
+            types.insert(name, parse_type_def(ty, &format!("{}.type", path))?);
+        }
+
+        let mut events = vec![];
+        for (i, event) in array(&idl, "events")?.iter().enumerate() {
+            let path = format!("events[{}]", i);
+            let name = str_field(event, "name", &path)?;
+            // 旧版 IDL 的事件字段直接写在 events 里，新版放在 types 中
+            let fields = match event.get("fields") {
+                Some(fields) => parse_fields(fields, &format!("{}.fields", path))?,
+                None => match types.get(&name) {
+                    Some(TypeDef::Struct(fields)) => fields.clone(),
+                    _ => return Err(idl_error(&path, &format!("no struct type {}", name))),
+                },
+            };
+            events.push(IdlItem {
+                discriminator: discriminator(event, &path, || format!("event:{}", name))?,
+                name,
+                fields,
+                accounts: vec![],
+            });
+        }
+
+        let mut instructions = vec![];
+        for (i, instruction) in array(&idl, "instructions")?.iter().enumerate() {
+            let path = format!("instructions[{}]", i);
+            let name = str_field(instruction, "name", &path)?;
+            let fields = match instruction.get("args") {
+                Some(args) => parse_fields(args, &format!("{}.args", path))?,
+                None => Fields::Named(vec![]),
+            };
+            let accounts = array(instruction, "accounts")?
+                .iter()
+                .filter_map(|account| account.get("name").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect();
+            instructions.push(IdlItem {
+                discriminator: discriminator(instruction, &path, || {
+                    format!("global:{}", to_snake_case(&name))
+                })?,
+                name,
+                fields,
+                accounts,
+            });
+        }
+
+        Ok(Self {
+            name,
+            address,
+            events,
+            instructions,
+            types,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn address(&self) -> Option<&Pubkey> {
+        self.address.as_ref()
+    }
+
+    // (事件名, discriminator)
+    pub fn events(&self) -> impl Iterator<Item = (&str, [u8; 8])> {
+        self.events
+            .iter()
+            .map(|event| (event.name.as_str(), event.discriminator))
+    }
+
+    pub fn instructions(&self) -> impl Iterator<Item = (&str, [u8; 8])> {
+        self.instructions
+            .iter()
+            .map(|instruction| (instruction.name.as_str(), instruction.discriminator))
+    }
+
+    // data 包含 discriminator，也可以是带 EVENT_IX_TAG 的 emit_cpi! 指令数据；
+    // discriminator 不属于这个 IDL 时返回 None
+    pub fn decode_event(&self, data: &[u8]) -> Result<Option<DecodedValue>, ClientError> {
+        let data = data.strip_prefix(&EVENT_IX_TAG[..]).unwrap_or(data);
+        let Some((discr, body)) = data.split_first_chunk::<8>() else {
+            return Ok(None);
+        };
+        let Some(event) = self.events.iter().find(|e| e.discriminator == *discr) else {
+            return Ok(None);
+        };
+        let value = self.decode_fields(&event.fields, &mut Reader::new(body), &event.name, 0)?;
+        Ok(Some(DecodedValue {
+            name: event.name.clone(),
+            value,
+        }))
+    }
+
+    pub fn decode_instruction(
+        &self,
+        data: &[u8],
+    ) -> Result<Option<DecodedIdlInstruction>, ClientError> {
+        let Some((discr, body)) = data.split_first_chunk::<8>() else {
+            return Ok(None);
+        };
+        let Some(instruction) = self.instructions.iter().find(|i| i.discriminator == *discr) else {
+            return Ok(None);
+        };
+        let args = self.decode_fields(
+            &instruction.fields,
+            &mut Reader::new(body),
+            &instruction.name,
+            0,
+        )?;
+        Ok(Some(DecodedIdlInstruction {
+            name: instruction.name.clone(),
+            args,
+            accounts: instruction.accounts.clone(),
+        }))
+    }
+
+    // 解析日志中所有属于这个 IDL 的事件，解析失败的跳过
+    pub fn decode_logs(&self, logs: &[String]) -> Vec<DecodedValue> {
+        logs.iter()
+            .filter_map(|log| log.strip_prefix(PROGRAM_DATA))
+            .filter_map(|payload| general_purpose::STANDARD.decode(payload).ok())
+            .filter_map(|data| self.decode_event(&data).ok().flatten())
+            .collect()
+    }
+
+    fn decode_fields(
+        &self,
+        fields: &Fields,
+        reader: &mut Reader,
+        path: &str,
+        depth: usize,
+    ) -> Result<Value, ClientError> {
+        match fields {
+            Fields::Named(fields) => {
+                let mut object = Map::new();
+                for (name, ty) in fields {
+                    let path = format!("{}.{}", path, name);
+                    object.insert(name.clone(), self.decode_type(ty, reader, &path, depth)?);
+                }
+                Ok(Value::Object(object))
+            }
+            Fields::Tuple(types) => types
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| self.decode_type(ty, reader, &format!("{}.{}", path, i), depth))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+        }
+    }
+
+    fn decode_type(
+        &self,
+        ty: &IdlType,
+        reader: &mut Reader,
+        path: &str,
+        depth: usize,
+    ) -> Result<Value, ClientError> {
+        if depth > MAX_DEPTH {
+            return Err(decode_error(path, "type nesting too deep"));
+        }
+        let value = match ty {
+            IdlType::Bool => match reader.take(1, path)?[0] {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                b => return Err(decode_error(path, &format!("invalid bool {}", b))),
+            },
+            IdlType::U8 => reader.take(1, path)?[0].into(),
+            IdlType::I8 => (reader.array::<1>(path)?[0] as i8).into(),
+            IdlType::U16 => u16::from_le_bytes(reader.array(path)?).into(),
+            IdlType::I16 => i16::from_le_bytes(reader.array(path)?).into(),
+            IdlType::U32 => u32::from_le_bytes(reader.array(path)?).into(),
+            IdlType::I32 => i32::from_le_bytes(reader.array(path)?).into(),
+            IdlType::U64 => u64::from_le_bytes(reader.array(path)?).into(),
+            IdlType::I64 => i64::from_le_bytes(reader.array(path)?).into(),
+            // JSON 数字放不下 128 位整数，用字符串表示
+            IdlType::U128 => u128::from_le_bytes(reader.array(path)?).to_string().into(),
+            IdlType::I128 => i128::from_le_bytes(reader.array(path)?).to_string().into(),
+            IdlType::F32 => f32::from_le_bytes(reader.array(path)?).into(),
+            IdlType::F64 => f64::from_le_bytes(reader.array(path)?).into(),
+            IdlType::String => {
+                let len = reader.len(path)?;
+                String::from_utf8(reader.take(len, path)?.to_vec())
+                    .map_err(|_| decode_error(path, "invalid utf-8"))?
+                    .into()
+            }
+            IdlType::Pubkey => bs58::encode(reader.take(32, path)?).into_string().into(),
+            IdlType::Bytes => {
+                let len = reader.len(path)?;
+                general_purpose::STANDARD
+                    .encode(reader.take(len, path)?)
+                    .into()
+            }
+            IdlType::Vec(inner) => {
+                let len = reader.len(path)?;
+                (0..len)
+                    .map(|i| {
+                        self.decode_type(inner, reader, &format!("{}[{}]", path, i), depth + 1)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into()
+            }
+            IdlType::Array(inner, len) => (0..*len)
+                .map(|i| self.decode_type(inner, reader, &format!("{}[{}]", path, i), depth + 1))
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            IdlType::Option(inner) => match reader.take(1, path)?[0] {
+                0 => Value::Null,
+                1 => self.decode_type(inner, reader, path, depth + 1)?,
+                b => return Err(decode_error(path, &format!("invalid option tag {}", b))),
+            },
+            IdlType::Defined(name) => match self.types.get(name) {
+                Some(TypeDef::Struct(fields)) => {
+                    self.decode_fields(fields, reader, path, depth + 1)?
+                }
+                Some(TypeDef::Enum(variants)) => {
+                    let tag = reader.take(1, path)?[0] as usize;
+                    let (variant, fields) = variants.get(tag).ok_or_else(|| {
+                        decode_error(path, &format!("invalid {} variant {}", name, tag))
+                    })?;
+                    match fields {
+                        Fields::Named(named) if named.is_empty() => variant.clone().into(),
+                        fields => {
+                            let mut object = Map::new();
+                            object.insert(
+                                variant.clone(),
+                                self.decode_fields(fields, reader, path, depth + 1)?,
+                            );
+                            Value::Object(object)
+                        }
+                    }
+                }
+                None => return Err(decode_error(path, &format!("undefined type {}", name))),
+            },
+        };
+        Ok(value)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, n: usize, path: &str) -> Result<&'a [u8], ClientError> {
+        let Some((head, rest)) = self.data.split_at_checked(n) else {
+            return Err(decode_error(
+                path,
+                &format!("needs {} bytes, {} left", n, self.data.len()),
+            ));
+        };
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self, path: &str) -> Result<[u8; N], ClientError> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N, path)?);
+        Ok(bytes)
+    }
+
+    // borsh 的长度前缀是 u32
+    fn len(&mut self, path: &str) -> Result<usize, ClientError> {
+        Ok(u32::from_le_bytes(self.array(path)?) as usize)
+    }
+}
+
+fn decode_error(path: &str, message: &str) -> ClientError {
+    ClientError::Decode(format!("{}: {}", path, message))
+}
+
+fn idl_error(path: &str, message: &str) -> ClientError {
+    ClientError::Config(format!("IDL {}: {}", path, message))
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> Result<&'a [Value], ClientError> {
+    match value.get(key) {
+        None => Ok(&[]),
+        Some(Value::Array(values)) => Ok(values),
+        Some(_) => Err(idl_error(key, "expected an array")),
+    }
+}
+
+fn str_field(value: &Value, key: &str, path: &str) -> Result<String, ClientError> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| idl_error(&format!("{}.{}", path, key), "expected a string"))
+}
+
+// 新版 IDL 直接给出 discriminator，旧版按 sha256(preimage) 的前 8 字节计算
+fn discriminator(
+    item: &Value,
+    path: &str,
+    preimage: impl FnOnce() -> String,
+) -> Result<[u8; 8], ClientError> {
+    match item.get("discriminator") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|_| idl_error(&format!("{}.discriminator", path), "expected 8 bytes")),
+        None => {
+            let mut discriminator = [0u8; 8];
+            discriminator.copy_from_slice(&hash(preimage().as_bytes()).to_bytes()[..8]);
+            Ok(discriminator)
+        }
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn parse_type_def(ty: &Value, path: &str) -> Result<TypeDef, ClientError> {
+    match ty.get("kind").and_then(Value::as_str) {
+        Some("struct") => Ok(TypeDef::Struct(match ty.get("fields") {
+            Some(fields) => parse_fields(fields, &format!("{}.fields", path))?,
+            None => Fields::Named(vec![]),
+        })),
+        Some("enum") => array(ty, "variants")?
+            .iter()
+            .enumerate()
+            .map(|(i, variant)| {
+                let path = format!("{}.variants[{}]", path, i);
+                let fields = match variant.get("fields") {
+                    Some(fields) => parse_fields(fields, &format!("{}.fields", path))?,
+                    None => Fields::Named(vec![]),
+                };
+                Ok((str_field(variant, "name", &path)?, fields))
+            })
+            .collect::<Result<_, _>>()
+            .map(TypeDef::Enum),
+        other => Err(idl_error(
+            &format!("{}.kind", path),
+            &format!("unsupported kind {:?}", other),
+        )),
+    }
+}
+
+// 具名字段是 {name, type} 对象，元组字段直接是类型
+fn parse_fields(fields: &Value, path: &str) -> Result<Fields, ClientError> {
+    let Value::Array(fields) = fields else {
+        return Err(idl_error(path, "expected an array"));
+    };
+    if fields.iter().all(|field| field.get("name").is_some()) {
+        fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let path = format!("{}[{}]", path, i);
+                let ty = field
+                    .get("type")
+                    .ok_or_else(|| idl_error(&path, "missing type"))?;
+                Ok((
+                    str_field(field, "name", &path)?,
+                    parse_type(ty, &format!("{}.type", path))?,
+                ))
+            })
+            .collect::<Result<_, _>>()
+            .map(Fields::Named)
+    } else {
+        fields
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| parse_type(ty, &format!("{}[{}]", path, i)))
+            .collect::<Result<_, _>>()
+            .map(Fields::Tuple)
+    }
+}
+
+fn parse_type(ty: &Value, path: &str) -> Result<IdlType, ClientError> {
+    let boxed = |key: &str| -> Result<Box<IdlType>, ClientError> {
+        Ok(Box::new(parse_type(
+            &ty[key],
+            &format!("{}.{}", path, key),
+        )?))
+    };
+    match ty {
+        Value::String(name) => Ok(match name.as_str() {
+            "bool" => IdlType::Bool,
+            "u8" => IdlType::U8,
+            "i8" => IdlType::I8,
+            "u16" => IdlType::U16,
+            "i16" => IdlType::I16,
+            "u32" => IdlType::U32,
+            "i32" => IdlType::I32,
+            "u64" => IdlType::U64,
+            "i64" => IdlType::I64,
+            "u128" => IdlType::U128,
+            "i128" => IdlType::I128,
+            "f32" => IdlType::F32,
+            "f64" => IdlType::F64,
+            "string" => IdlType::String,
+            "publicKey" | "pubkey" => IdlType::Pubkey,
+            "bytes" => IdlType::Bytes,
+            other => return Err(idl_error(path, &format!("unknown type {}", other))),
+        }),
+        Value::Object(object) if object.contains_key("vec") => Ok(IdlType::Vec(boxed("vec")?)),
+        Value::Object(object) if object.contains_key("option") => {
+            Ok(IdlType::Option(boxed("option")?))
+        }
+        Value::Object(object) if object.contains_key("array") => match &object["array"] {
+            Value::Array(array) if array.len() == 2 => Ok(IdlType::Array(
+                Box::new(parse_type(&array[0], &format!("{}.array", path))?),
+                array[1]
+                    .as_u64()
+                    .ok_or_else(|| idl_error(path, "array length must be a number"))?
+                    as usize,
+            )),
+            _ => Err(idl_error(path, "expected [type, length]")),
+        },
+        // 旧版 {"defined": "Name"}，新版 {"defined": {"name": "Name"}}
+        Value::Object(object) if object.contains_key("defined") => match &object["defined"] {
+            Value::String(name) => Ok(IdlType::Defined(name.clone())),
+            defined => Ok(IdlType::Defined(str_field(defined, "name", path)?)),
+        },
+        _ => Err(idl_error(path, &format!("unsupported type {}", ty))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+
+    use crate::model::{
+        EventTrait,
+        pumpfun_model::{CreateEvent, TradeEvent},
+    };
+
+    // 新版格式：字段在 types 中，指令带 discriminator
+    const PUMP_IDL: &str = r#"{
+        "address": "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P",
+        "metadata": { "name": "pump" },
+        "instructions": [{
+            "name": "buy",
+            "discriminator": [102, 6, 61, 18, 1, 218, 235, 234],
+            "accounts": [{ "name": "global" }, { "name": "fee_recipient" }],
+            "args": [
+                { "name": "amount", "type": "u64" },
+                { "name": "max_sol_cost", "type": "u64" }
+            ]
+        }],
+        "events": [{ "name": "CreateEvent" }, { "name": "TradeEvent" }],
+        "types": [
+            { "name": "CreateEvent", "type": { "kind": "struct", "fields": [
+                { "name": "name", "type": "string" },
+                { "name": "symbol", "type": "string" },
+                { "name": "uri", "type": "string" },
+                { "name": "mint", "type": "pubkey" },
+                { "name": "bonding_curve", "type": "pubkey" },
+                { "name": "user", "type": "pubkey" },
+                { "name": "creator", "type": "pubkey" },
+                { "name": "timestamp", "type": "i64" },
+                { "name": "virtual_token_reserves", "type": "u64" },
+                { "name": "virtual_sol_reserves", "type": "u64" },
+                { "name": "real_token_reserves", "type": "u64" },
+                { "name": "token_total_supply", "type": "u64" }
+            ]}},
+            { "name": "TradeEvent", "type": { "kind": "struct", "fields": [
+                { "name": "mint", "type": "pubkey" },
+                { "name": "sol_amount", "type": "u64" }
+            ]}}
+        ]
+    }"#;
+
+    #[test]
+    fn test_discriminators_match_models() {
+        let idl = IdlDecoder::from_json(PUMP_IDL).unwrap();
+        let events: HashMap<_, _> = idl.events().collect();
+        assert_eq!(events["CreateEvent"], CreateEvent::discriminator());
+        assert_eq!(events["TradeEvent"], TradeEvent::discriminator());
+        assert_eq!(to_snake_case("createPool"), "create_pool");
+    }
+
+    #[test]
+    fn test_decode_event_and_instruction() {
+        let idl = IdlDecoder::from_json(PUMP_IDL).unwrap();
+        let create = CreateEvent {
+            name: "Token".to_string(),
+            symbol: "TKN".to_string(),
+            timestamp: -5,
+            token_total_supply: 1_000_000,
+            ..Default::default()
+        };
+        let mut data = CreateEvent::discriminator().to_vec();
+        create.serialize(&mut data).unwrap();
+
+        let decoded = idl.decode_event(&data).unwrap().unwrap();
+        assert_eq!(decoded.name, "CreateEvent");
+        assert_eq!(decoded.value["symbol"], "TKN");
+        assert_eq!(decoded.value["timestamp"], -5);
+        assert_eq!(decoded.value["token_total_supply"], 1_000_000);
+        assert_eq!(decoded.value["mint"], Pubkey::default().to_string());
+
+        let error = idl.decode_event(&data[..20]).unwrap_err();
+        assert!(error.to_string().contains("CreateEvent.symbol"));
+
+        let mut buy = vec![102, 6, 61, 18, 1, 218, 235, 234];
+        buy.extend(7u64.to_le_bytes());
+        buy.extend(9u64.to_le_bytes());
+        let instruction = idl.decode_instruction(&buy).unwrap().unwrap();
+        assert_eq!(instruction.args["max_sol_cost"], 9);
+        assert_eq!(instruction.accounts, ["global", "fee_recipient"]);
+    }
+
+    #[test]
+    fn test_legacy_idl_with_defined_types() {
+        let idl = IdlDecoder::from_json(
+            r#"{
+                "name": "demo",
+                "instructions": [{ "name": "setMode", "accounts": [], "args": [
+                    { "name": "mode", "type": { "defined": "Mode" } }
+                ]}],
+                "types": [{ "name": "Mode", "type": { "kind": "enum", "variants": [
+                    { "name": "Off" },
+                    { "name": "Limit", "fields": [{ "name": "max", "type": { "option": "u16" } }] }
+                ]}}],
+                "events": [{ "name": "Ticks", "fields": [
+                    { "name": "values", "type": { "vec": "u8" }, "index": false },
+                    { "name": "pair", "type": { "array": ["bool", 2] }, "index": false }
+                ]}]
+            }"#,
+        )
+        .unwrap();
+
+        let (_, discriminator) = idl.instructions().next().unwrap();
+        assert_eq!(discriminator, hash(b"global:set_mode").to_bytes()[..8]);
+        let mut data = discriminator.to_vec();
+        data.extend([1, 1, 0x10, 0x00]);
+        let instruction = idl.decode_instruction(&data).unwrap().unwrap();
+        assert_eq!(
+            instruction.args,
+            serde_json::json!({ "mode": { "Limit": { "max": 16 } } })
+        );
+
+        let (_, discriminator) = idl.events().next().unwrap();
+        let mut data = discriminator.to_vec();
+        data.extend([2, 0, 0, 0, 5, 6, 1, 0]);
+        let log = format!("Program data: {}", general_purpose::STANDARD.encode(&data));
+        let events = idl.decode_logs(&[log]);
+        assert_eq!(
+            events[0].value,
+            serde_json::json!({ "values": [5, 6], "pair": [true, false] })
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::info;
+use yellowstone_grpc_proto::prost_types::Timestamp;
+
+use crate::shutdown::CancellationToken;
+
+// 桶上限（毫秒），最后一个桶收集所有更大的值
+const BUCKETS_MS: [u64; 16] = [
+    1,
+    2,
+    5,
+    10,
+    20,
+    50,
+    100,
+    200,
+    500,
+    1_000,
+    2_000,
+    5_000,
+    10_000,
+    30_000,
+    60_000,
+    u64::MAX,
+];
+
+// created_at 到 now 的耗时；本地时钟比服务端慢时记为 0
+pub fn latency_since(created_at: &Timestamp, now: SystemTime) -> Option<Duration> {
+    let secs = u64::try_from(created_at.seconds).ok()?;
+    let nanos = u32::try_from(created_at.nanos).ok()?;
+    let created_at = UNIX_EPOCH + Duration::new(secs, nanos);
+    Some(now.duration_since(created_at).unwrap_or_default())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LatencyStage {
+    // 服务端生成更新到本地收到
+    Receive,
+    // 服务端生成更新到事件解析完成
+    Decode,
+}
+
+impl fmt::Display for LatencyStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Receive => write!(f, "receive"),
+            Self::Decode => write!(f, "decode"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [u64; BUCKETS_MS.len()],
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        let index = BUCKETS_MS
+            .iter()
+            .position(|&bound| ms < bound)
+            .unwrap_or(BUCKETS_MS.len() - 1);
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        // 按纳秒计算，count 超过 u32 时不会截断
+        Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    // 返回分位数所在桶的上限，精度取决于桶的划分
+    pub fn quantile(&self, q: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((self.count as f64 * q).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return match BUCKETS_MS[index] {
+                    u64::MAX => self.max,
+                    bound => Duration::from_millis(bound).min(self.max),
+                };
+            }
+        }
+        self.max
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatencySummary {
+    pub stage: LatencyStage,
+    // Receive 为过滤器名称，Decode 为事件类型
+    pub key: String,
+    pub count: u64,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+// 按阶段和过滤器 / 事件类型分别统计延迟，可以在多个任务间共享
+#[derive(Clone, Debug, Default)]
+pub struct LatencyTracker {
+    histograms: Arc<Mutex<BTreeMap<(LatencyStage, String), LatencyHistogram>>>,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, stage: LatencyStage, key: &str, latency: Duration) {
+        let mut histograms = self.histograms.lock().unwrap();
+        match histograms.get_mut(&(stage, key.to_string())) {
+            Some(histogram) => histogram.record(latency),
+            None => {
+                let mut histogram = LatencyHistogram::default();
+                histogram.record(latency);
+                histograms.insert((stage, key.to_string()), histogram);
+            }
+        }
+    }
+
+    // 按更新匹配到的每个过滤器记录一次
+    pub fn record_receive(&self, filters: &[String], created_at: &Timestamp, now: SystemTime) {
+        let Some(latency) = latency_since(created_at, now) else {
+            return;
+        };
+        for filter in filters {
+            self.record(LatencyStage::Receive, filter, latency);
+        }
+    }
+
+    pub fn record_decode(&self, event_type: &str, created_at: &Timestamp, now: SystemTime) {
+        if let Some(latency) = latency_since(created_at, now) {
+            self.record(LatencyStage::Decode, event_type, latency);
+        }
+    }
+
+    pub fn histogram(&self, stage: LatencyStage, key: &str) -> Option<LatencyHistogram> {
+        self.histograms
+            .lock()
+            .unwrap()
+            .get(&(stage, key.to_string()))
+            .cloned()
+    }
+
+    pub fn summaries(&self) -> Vec<LatencySummary> {
+        self.histograms
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((stage, key), histogram)| LatencySummary {
+                stage: *stage,
+                key: key.clone(),
+                count: histogram.count(),
+                mean: histogram.mean(),
+                p50: histogram.quantile(0.5),
+                p99: histogram.quantile(0.99),
+                max: histogram.max(),
+            })
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        self.histograms.lock().unwrap().clear();
+    }
+
+    pub fn log_summary(&self) {
+        for summary in self.summaries() {
+            info!(
+                "latency {} {}: count: {}, mean: {:?}, p50: {:?}, p99: {:?}, max: {:?}",
+                summary.stage,
+                summary.key,
+                summary.count,
+                summary.mean,
+                summary.p50,
+                summary.p99,
+                summary.max
+            );
+        }
+    }
+
+    // 定期打印统计，token 取消后打印最后一次并返回
+    pub async fn report(self, interval: Duration, shutdown: CancellationToken) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => self.log_summary(),
+                _ = shutdown.cancelled() => break,
+            }
+        }
+        self.log_summary();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_quantiles() {
+        let mut histogram = LatencyHistogram::default();
+        for ms in [3, 4, 4, 8, 150] {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.quantile(0.5), Duration::from_millis(5));
+        assert_eq!(histogram.quantile(0.99), Duration::from_millis(150));
+        assert_eq!(histogram.max(), Duration::from_millis(150));
+
+        // count 超过 u32::MAX 时平均值仍然正确
+        let histogram = LatencyHistogram {
+            count: 1 << 32,
+            total: Duration::from_secs(3 << 32),
+            ..Default::default()
+        };
+        assert_eq!(histogram.mean(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_latency_from_created_at() {
+        let created_at = Timestamp {
+            seconds: 1_700_000_000,
+            nanos: 500_000_000,
+        };
+        let now = UNIX_EPOCH + Duration::from_millis(1_700_000_000_750);
+        assert_eq!(
+            latency_since(&created_at, now),
+            Some(Duration::from_millis(250))
+        );
+
+        let tracker = LatencyTracker::new();
+        tracker.record_receive(&["pump".to_string()], &created_at, now);
+        tracker.record_decode("pump_trade", &created_at, now);
+        let summaries = tracker.summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].stage, LatencyStage::Receive);
+        assert_eq!(summaries[0].key, "pump");
+        // 时钟偏差导致 created_at 晚于本地时间
+        assert_eq!(latency_since(&created_at, UNIX_EPOCH), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,32 @@
+// SolanaEvent 生成的代码通过 ::grpc_jh 引用这个 crate
+extern crate self as grpc_jh;
+
+solana_events!();
+
+pub mod checkpoint;
+pub mod cli;
+mod common;
+pub mod config;
+pub mod decode;
+pub mod engine;
+pub mod error;
+pub mod grpc;
+pub mod handle;
+pub mod idl;
+pub mod latency;
+pub mod lifecycle;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod model;
+pub mod multi;
+pub mod output;
+pub mod pipeline;
+pub mod reconnect;
+pub mod record;
+pub mod request;
+pub mod router;
+pub mod rpc;
+pub mod settings;
+pub mod shutdown;
+pub mod slots;
+pub mod subscription;
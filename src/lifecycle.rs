@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+
+use log::warn;
+use tokio::sync::broadcast;
+use yellowstone_grpc_proto::geyser::{
+    SlotStatus, SubscribeRequest, SubscribeRequestFilterSlots, SubscribeUpdateSlot,
+};
+
+// 生命周期跟踪使用的 slot 过滤器名称
+pub const LIFECYCLE_FILTER: &str = "lifecycle";
+// 保留多少个 slot 的状态，用于处理晚到的交易；比最新 slot 落后更多的待确认交易直接丢弃
+const HISTORY_SLOTS: u64 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EventStage {
+    Processed,
+    Confirmed,
+    Finalized,
+    // slot 被标记为 dead，或者最终确认的 slot 沿父链回溯时跳过了它
+    RolledBack,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LifecycleNotification {
+    pub signature: String,
+    pub slot: u64,
+    pub stage: EventStage,
+}
+
+// 事件在 processed 时立即上报，之后根据 slot 状态更新继续上报 Confirmed / Finalized / RolledBack
+#[derive(Clone, Debug)]
+pub struct CommitmentTracker {
+    pending: BTreeMap<u64, Vec<String>>,
+    stages: BTreeMap<u64, EventStage>,
+    // slot 更新中带的父 slot，用于判断某个 slot 是否在最终确认的链上
+    parents: BTreeMap<u64, u64>,
+    // 见过的最大 slot
+    tip: u64,
+    notifications: broadcast::Sender<LifecycleNotification>,
+}
+
+impl CommitmentTracker {
+    pub fn new(notifications: broadcast::Sender<LifecycleNotification>) -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            stages: BTreeMap::new(),
+            parents: BTreeMap::new(),
+            tip: 0,
+            notifications,
+        }
+    }
+
+    // 订阅所有状态的 slot 更新
+    pub fn apply(request: &mut SubscribeRequest) {
+        request.slots.insert(
+            LIFECYCLE_FILTER.to_string(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(false),
+                interslot_updates: Some(false),
+            },
+        );
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LifecycleNotification> {
+        self.notifications.subscribe()
+    }
+
+    // 尚未最终确认的交易数量
+    pub fn pending(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    pub fn track(&mut self, slot: u64, signature: String) -> Vec<LifecycleNotification> {
+        let mut notifications = vec![LifecycleNotification {
+            signature: signature.clone(),
+            slot,
+            stage: EventStage::Processed,
+        }];
+
+        // slot 状态可能先于交易到达
+        let stage = self.stages.get(&slot).copied();
+        if let Some(stage) = stage {
+            if stage == EventStage::Finalized {
+                notifications.push(LifecycleNotification {
+                    signature: signature.clone(),
+                    slot,
+                    stage: EventStage::Confirmed,
+                });
+            }
+            notifications.push(LifecycleNotification {
+                signature: signature.clone(),
+                slot,
+                stage,
+            });
+        }
+        if !matches!(
+            stage,
+            Some(EventStage::Finalized) | Some(EventStage::RolledBack)
+        ) {
+            self.pending.entry(slot).or_default().push(signature);
+            self.advance_tip(slot);
+        }
+
+        self.publish(&notifications);
+        notifications
+    }
+
+    pub fn on_slot(&mut self, update: &SubscribeUpdateSlot) -> Vec<LifecycleNotification> {
+        // 各个状态的更新都可能带父 slot
+        if let Some(parent) = update.parent {
+            self.parents.insert(update.slot, parent);
+        }
+        self.advance_tip(update.slot);
+
+        let stage = match SlotStatus::try_from(update.status) {
+            Ok(SlotStatus::SlotConfirmed) => EventStage::Confirmed,
+            Ok(SlotStatus::SlotFinalized) => EventStage::Finalized,
+            Ok(SlotStatus::SlotDead) => EventStage::RolledBack,
+            _ => return vec![],
+        };
+        let slot = update.slot;
+        if self
+            .stages
+            .get(&slot)
+            .is_some_and(|&previous| previous >= stage)
+        {
+            return vec![];
+        }
+        self.stages.insert(slot, stage);
+
+        let mut notifications = vec![];
+        let signatures = match stage {
+            EventStage::Confirmed => self.pending.get(&slot).cloned().unwrap_or_default(),
+            _ => self.pending.remove(&slot).unwrap_or_default(),
+        };
+        notifications.extend(
+            signatures
+                .into_iter()
+                .map(|signature| LifecycleNotification {
+                    signature,
+                    slot,
+                    stage,
+                }),
+        );
+
+        if stage == EventStage::Finalized {
+            self.settle_ancestors(slot, &mut notifications);
+            let floor = slot.saturating_sub(HISTORY_SLOTS);
+            self.stages = self.stages.split_off(&floor);
+            self.parents = self.parents.split_off(&floor);
+        }
+
+        self.publish(&notifications);
+        notifications
+    }
+
+    // 从最终确认的 slot 沿父链回溯：链上的 slot 同样已最终确认，
+    // 相邻两个链上 slot 之间的 slot 被跳过，不会再被确认。
+    // 父链中断（没有收到父 slot）时停止，更早的 slot 等待 SlotDead 或下一次回溯
+    fn settle_ancestors(&mut self, slot: u64, notifications: &mut Vec<LifecycleNotification>) {
+        let mut current = slot;
+        while let Some(&parent) = self.parents.get(&current) {
+            let Some((&oldest, _)) = self.pending.first_key_value() else {
+                break;
+            };
+            if current <= oldest {
+                break;
+            }
+            let mut skipped = self.pending.split_off(&(parent + 1));
+            let above = skipped.split_off(&current);
+            self.pending.extend(above);
+            for (orphan, signatures) in skipped {
+                self.stages.insert(orphan, EventStage::RolledBack);
+                notifications.extend(signatures.into_iter().map(|signature| {
+                    LifecycleNotification {
+                        signature,
+                        slot: orphan,
+                        stage: EventStage::RolledBack,
+                    }
+                }));
+            }
+
+            if self
+                .stages
+                .get(&parent)
+                .is_none_or(|&stage| stage < EventStage::Finalized)
+            {
+                self.stages.insert(parent, EventStage::Finalized);
+                let signatures = self.pending.remove(&parent).unwrap_or_default();
+                notifications.extend(signatures.into_iter().map(|signature| {
+                    LifecycleNotification {
+                        signature,
+                        slot: parent,
+                        stage: EventStage::Finalized,
+                    }
+                }));
+            }
+            current = parent;
+        }
+    }
+
+    // 一直没有收到最终状态的交易不能无限保留
+    fn advance_tip(&mut self, slot: u64) {
+        if slot <= self.tip {
+            return;
+        }
+        self.tip = slot;
+        let remaining = self.pending.split_off(&slot.saturating_sub(HISTORY_SLOTS));
+        let expired = std::mem::replace(&mut self.pending, remaining);
+        let count: usize = expired.values().map(Vec::len).sum();
+        if count > 0 {
+            warn!(
+                "Dropping {} transactions without a final slot status ({} slots behind {})",
+                count, HISTORY_SLOTS, slot
+            );
+        }
+    }
+
+    fn publish(&self, notifications: &[LifecycleNotification]) {
+        for notification in notifications {
+            // 没有接收者时忽略
+            let _ = self.notifications.send(notification.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(slot: u64, status: SlotStatus) -> SubscribeUpdateSlot {
+        SubscribeUpdateSlot {
+            slot,
+            status: status as i32,
+            ..Default::default()
+        }
+    }
+
+    fn child(slot: u64, parent: u64) -> SubscribeUpdateSlot {
+        SubscribeUpdateSlot {
+            slot,
+            parent: Some(parent),
+            status: SlotStatus::SlotFirstShredReceived as i32,
+            ..Default::default()
+        }
+    }
+
+    fn stages(notifications: Vec<LifecycleNotification>) -> Vec<(String, EventStage)> {
+        notifications
+            .into_iter()
+            .map(|n| (n.signature, n.stage))
+            .collect()
+    }
+
+    #[test]
+    fn test_confirm_finalize_and_rollback() {
+        let (tx, _) = broadcast::channel(16);
+        let mut tracker = CommitmentTracker::new(tx);
+        tracker.track(10, "a".to_string());
+        tracker.track(11, "b".to_string());
+        tracker.track(12, "c".to_string());
+        tracker.on_slot(&child(12, 10));
+
+        assert_eq!(
+            stages(tracker.on_slot(&slot(10, SlotStatus::SlotConfirmed))),
+            vec![("a".to_string(), EventStage::Confirmed)]
+        );
+        assert_eq!(
+            stages(tracker.on_slot(&slot(10, SlotStatus::SlotFinalized))),
+            vec![("a".to_string(), EventStage::Finalized)]
+        );
+        // 12 的父 slot 是 10，11 被跳过
+        assert_eq!(
+            stages(tracker.on_slot(&slot(12, SlotStatus::SlotFinalized))),
+            vec![
+                ("c".to_string(), EventStage::Finalized),
+                ("b".to_string(), EventStage::RolledBack),
+            ]
+        );
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    #[test]
+    fn test_late_transaction_and_dead_slot() {
+        let (tx, _) = broadcast::channel(16);
+        let mut tracker = CommitmentTracker::new(tx);
+        tracker.on_slot(&slot(20, SlotStatus::SlotConfirmed));
+        assert_eq!(
+            stages(tracker.track(20, "a".to_string())),
+            vec![
+                ("a".to_string(), EventStage::Processed),
+                ("a".to_string(), EventStage::Confirmed),
+            ]
+        );
+
+        tracker.track(21, "b".to_string());
+        assert_eq!(
+            stages(tracker.on_slot(&slot(21, SlotStatus::SlotDead))),
+            vec![("b".to_string(), EventStage::RolledBack)]
+        );
+        assert_eq!(tracker.pending(), 1);
+    }
+
+    #[test]
+    fn test_rollback_needs_parent_chain() {
+        let (tx, _) = broadcast::channel(16);
+        let mut tracker = CommitmentTracker::new(tx);
+        tracker.track(30, "a".to_string());
+        tracker.track(31, "b".to_string());
+        tracker.track(32, "c".to_string());
+
+        // 不知道 33 的父 slot 时无法判断 30..32 是否在链上
+        assert!(
+            tracker
+                .on_slot(&slot(33, SlotStatus::SlotFinalized))
+                .is_empty()
+        );
+        assert_eq!(tracker.pending(), 3);
+
+        // 34 -> 32 -> 30：32 和 30 随之最终确认，31 被跳过
+        tracker.on_slot(&child(32, 30));
+        tracker.on_slot(&child(34, 32));
+        assert_eq!(
+            stages(tracker.on_slot(&slot(34, SlotStatus::SlotFinalized))),
+            vec![
+                ("c".to_string(), EventStage::Finalized),
+                ("b".to_string(), EventStage::RolledBack),
+                ("a".to_string(), EventStage::Finalized),
+            ]
+        );
+        assert_eq!(tracker.pending(), 0);
+    }
+
+    #[test]
+    fn test_pending_is_capped() {
+        let (tx, _) = broadcast::channel(16);
+        let mut tracker = CommitmentTracker::new(tx);
+        tracker.track(100, "a".to_string());
+        tracker.track(100 + HISTORY_SLOTS, "b".to_string());
+        assert_eq!(tracker.pending(), 2);
+        tracker.on_slot(&slot(101 + HISTORY_SLOTS, SlotStatus::SlotProcessed));
+        assert_eq!(tracker.pending(), 1);
+    }
+}
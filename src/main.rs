@@ -1,42 +1,204 @@
-use crate::grpc::YellowstoneGrpc;
+use grpc_jh::{
+    checkpoint::CheckpointConfig,
+    cli::{self, Command},
+    config::GrpcConfig,
+    decode::decode_input_with,
+    engine::UpdateDispatcher,
+    error::ClientError,
+    grpc::YellowstoneGrpc,
+    idl::IdlDecoder,
+    multi::MultiSubscriber,
+    output::{EventPrinter, OutputFormat, OutputWriter},
+    record::{UpdateReader, UpdateRecorder},
+    settings::Settings,
+    shutdown::Shutdown,
+};
+use std::{error::Error, fs, path::PathBuf, sync::Arc, time::Duration};
 
-mod common;
-mod grpc;
-mod handle;
-mod model;
+use tokio::sync::Mutex;
+use yellowstone_grpc_proto::geyser::SubscribeRequest;
 
-#[allow(unused)]
 // mod test;
 
-// const PROGRAM_ID1: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
-// const PROGRAM_ID2: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
-const PROGRAM_ID3: &str = "BVdVonejnHwKAVFKx1YpQaBc8t225hFuzjns5ZMEq3Pp";
+const LATENCY_REPORT_INTERVAL: Duration = Duration::from_secs(60);
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), Box<dyn Error>> {
     dotenv::dotenv().ok();
     pretty_env_logger::init();
 
-    let url = std::env::var("YELLOWSTONE_GRPC_URL").expect("YELLOWSTONE_GRPC_UTL must be set");
-    // let mut client = YellowstoneGrpc::new(url, None).build_client().await?;
-    let client = YellowstoneGrpc::new(url, None);
+    let command = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    // debug!("Starting subscription for Pump: {}", PROGRAM_ID1);
-    // debug!("Starting subscription for PumpAmm: {}", PROGRAM_ID2);
-
-    // 创建一个包含两个程序 ID 的向量
-    let program_ids = vec![PROGRAM_ID3];
+    match command {
+        Command::Help => println!("{}", cli::USAGE),
+        Command::Decode {
+            payloads,
+            file,
+            idls,
+            format,
+        } => decode(payloads, file, idls, format)?,
+        Command::Replay { input, format } => replay(input, format).await?,
+        Command::Watch {
+            filters,
+            format,
+            checkpoint,
+        } => {
+            let request = filters.request()?;
+            let mut shutdown = Shutdown::new();
+            let clients = connect(&shutdown)?;
+            // 配置了多个端点时合并订阅，按签名去重；事件只写日志
+            if clients.len() > 1 {
+                if format.is_some() || checkpoint.is_some() {
+                    return Err(ClientError::Config(
+                        "--format and --checkpoint are not supported with multiple endpoints"
+                            .to_string(),
+                    )
+                    .into());
+                }
+                shutdown.spawn(async move {
+                    if let Err(e) = MultiSubscriber::new(clients).subscribe(request).await {
+                        log::error!("Error subscribing to multiple endpoints: {:?}", e);
+                    }
+                });
+                return run_until_signal(shutdown).await;
+            }
 
-    // 订阅所有程序
-    for program_id in program_ids {
-        let client = client.clone();
-        tokio::spawn(async move {
-            if let Err(e) = client.subscribe_price().await {
-                log::error!("Error subscribing to program {}: {:?}", program_id, e);
+            let mut client = clients[0].clone();
+            if let Some(path) = checkpoint {
+                client = client.with_checkpoint(CheckpointConfig::new(path, "watch"));
+            }
+            shutdown.spawn(
+                client
+                    .latency()
+                    .report(LATENCY_REPORT_INTERVAL, shutdown.token()),
+            );
+            // 一个连接上的所有过滤器，按名称选择解析器
+            let router = cli::watch_router(EventPrinter::stdout(format.unwrap_or_default()));
+            spawn_subscription(&mut shutdown, client, request, Arc::new(Mutex::new(router)));
+            run_until_signal(shutdown).await?;
+        }
+        Command::Record { filters, output } => {
+            let request = filters.request()?;
+            let mut shutdown = Shutdown::new();
+            let client = connect(&shutdown)?.remove(0);
+            let recorder = Arc::new(Mutex::new(UpdateRecorder::create(&output)?));
+            spawn_subscription(&mut shutdown, client, request, recorder.clone());
+            run_until_signal(shutdown).await?;
+            log::info!(
+                "Recorded {} updates to {}",
+                recorder.lock().await.recorded(),
+                output.display()
+            );
+        }
+        Command::Accounts { filters, format } => {
+            let request = filters.request()?;
+            let mut shutdown = Shutdown::new();
+            let client = connect(&shutdown)?.remove(0);
+            let printer = Arc::new(Mutex::new(EventPrinter::stdout(format)));
+            spawn_subscription(&mut shutdown, client, request, printer);
+            run_until_signal(shutdown).await?;
+        }
+        Command::Run { config } => {
+            let settings = Settings::load(&config)?;
+            let mut shutdown = Shutdown::new();
+            // 所有订阅共用一组输出
+            let printers = Arc::new(Mutex::new(settings.printers()?));
+            for (name, subscription) in &settings.subscriptions {
+                let client = YellowstoneGrpc::from_config(
+                    settings.endpoints[&subscription.endpoint].clone(),
+                )?
+                .with_shutdown(shutdown.token());
+                log::info!(
+                    "Starting subscription {} on endpoint {}",
+                    name,
+                    subscription.endpoint
+                );
+                spawn_subscription(
+                    &mut shutdown,
+                    client,
+                    subscription.request.clone(),
+                    printers.clone(),
+                );
             }
-        });
+            run_until_signal(shutdown).await?;
+        }
+    }
+    Ok(())
+}
+
+fn connect(shutdown: &Shutdown) -> Result<Vec<YellowstoneGrpc>, Box<dyn Error>> {
+    Ok(GrpcConfig::from_env_multi()?
+        .into_iter()
+        .map(|config| {
+            YellowstoneGrpc::from_config(config)
+                .map(|client| client.with_shutdown(shutdown.token()))
+        })
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+fn spawn_subscription<D>(
+    shutdown: &mut Shutdown,
+    client: YellowstoneGrpc,
+    request: SubscribeRequest,
+    dispatcher: Arc<Mutex<D>>,
+) where
+    D: UpdateDispatcher + 'static,
+{
+    shutdown.track_queue(client.queue_metrics());
+    shutdown.spawn(async move {
+        if let Err(e) = client.run(request, dispatcher).await {
+            log::error!("Subscription stopped: {:?}", e);
+        }
+    });
+}
+
+async fn run_until_signal(shutdown: Shutdown) -> Result<(), Box<dyn Error>> {
+    shutdown.wait_for_signal().await?;
+    let summary = shutdown.shutdown().await;
+    log::info!("Shutdown complete: {:?}", summary);
+    Ok(())
+}
+
+// 参数和文件内容可以是 base64、日志行或 JSON 日志数组
+fn decode(
+    payloads: Vec<String>,
+    file: Option<PathBuf>,
+    idls: Vec<PathBuf>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let idls = idls
+        .iter()
+        .map(IdlDecoder::load)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut decoded = vec![];
+    for payload in payloads {
+        decoded.extend(decode_input_with(&payload, &idls)?);
+    }
+    if let Some(path) = file {
+        decoded.extend(decode_input_with(&fs::read_to_string(path)?, &idls)?);
+    }
+    if decoded.is_empty() {
+        eprintln!("no \"Program data:\" lines or payloads found in the input");
     }
 
-    tokio::signal::ctrl_c().await?;
+    let mut output = OutputWriter::stdout(format);
+    for payload in &decoded {
+        output.write_row(&payload.fields())?;
+    }
+    output.flush()?;
     Ok(())
 }
+
+async fn replay(input: PathBuf, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let mut printer = EventPrinter::stdout(format);
+    for update in UpdateReader::open(input)? {
+        printer.on_update(update?).await?;
+    }
+    Ok(printer.flush().await?)
+}
@@ -0,0 +1,372 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use base64::{Engine, engine::general_purpose};
+use futures_util::{Stream, stream};
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use yellowstone_grpc_proto::{
+    geyser::{
+        GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
+        GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
+        GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+        PongResponse, SubscribeReplayInfoRequest, SubscribeReplayInfoResponse, SubscribeRequest,
+        SubscribeUpdate, SubscribeUpdatePing, SubscribeUpdatePong, SubscribeUpdateSlot,
+        SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo,
+        geyser_server::{Geyser, GeyserServer},
+        subscribe_update::UpdateOneof,
+    },
+    prelude::TransactionStatusMeta,
+    tonic::{self, Request, Response, Status, Streaming, transport::Server},
+};
+
+use crate::grpc::YellowstoneGrpc;
+
+const STREAM_CHANNEL_SIZE: usize = 64;
+
+// 单个连接上按顺序执行的脚本步骤
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum MockStep {
+    Update(SubscribeUpdate),
+    // 返回错误状态并结束流
+    Error(Status),
+    // 直接结束流，模拟服务端断开
+    Disconnect,
+    Sleep(Duration),
+}
+
+// 一元 RPC 返回的链上状态
+#[derive(Clone, Debug)]
+pub struct MockChain {
+    pub slot: u64,
+    pub block_height: u64,
+    pub blockhash: String,
+    pub first_available: Option<u64>,
+    // GetLatestBlockhash 返回的 last_valid_block_height，None 时为 block_height + 150
+    pub last_valid_block_height: Option<u64>,
+    // IsBlockhashValid 的返回值，None 时按是否等于 blockhash 判断
+    pub blockhash_valid: Option<bool>,
+    pub version: String,
+}
+
+impl Default for MockChain {
+    fn default() -> Self {
+        Self {
+            slot: 1_000,
+            block_height: 900,
+            blockhash: "11111111111111111111111111111111".to_string(),
+            first_available: Some(0),
+            last_valid_block_height: None,
+            blockhash_valid: None,
+            version: r#"{"version":{"package":"mock-geyser"}}"#.to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    // 第 n 个连接使用第 n 个脚本，脚本用完后连接保持空闲
+    scripts: Mutex<VecDeque<Vec<MockStep>>>,
+    connections: AtomicUsize,
+    requests: Mutex<Vec<SubscribeRequest>>,
+    chain: Mutex<MockChain>,
+}
+
+// 进程内的 Geyser 服务，按脚本回放 SubscribeUpdate，用于测试和离线开发
+#[derive(Clone, Default)]
+pub struct MockGeyser {
+    state: Arc<MockState>,
+}
+
+impl MockGeyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_script(self, steps: Vec<MockStep>) -> Self {
+        self.state.scripts.lock().unwrap().push_back(steps);
+        self
+    }
+
+    pub fn with_chain(self, chain: MockChain) -> Self {
+        *self.state.chain.lock().unwrap() = chain;
+        self
+    }
+
+    fn chain(&self) -> MockChain {
+        self.state.chain.lock().unwrap().clone()
+    }
+
+    // 监听 127.0.0.1 的随机端口
+    pub async fn serve(self) -> Result<MockServer, Box<dyn Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let incoming = stream::unfold(listener, |listener| async move {
+            let accepted = listener.accept().await.map(|(socket, _)| socket);
+            Some((accepted, listener))
+        });
+
+        let state = self.state.clone();
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            let result = Server::builder()
+                .add_service(GeyserServer::new(self))
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await;
+            if let Err(e) = result {
+                log::error!("Mock geyser server stopped: {:?}", e);
+            }
+        });
+
+        Ok(MockServer {
+            endpoint,
+            state,
+            shutdown: Some(shutdown),
+            task,
+        })
+    }
+}
+
+pub struct MockServer {
+    endpoint: String,
+    state: Arc<MockState>,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn client(&self) -> YellowstoneGrpc {
+        YellowstoneGrpc::new(self.endpoint.clone(), None)
+    }
+
+    // 已建立的订阅连接数
+    pub fn connections(&self) -> usize {
+        self.state.connections.load(Ordering::Relaxed)
+    }
+
+    // 客户端发来的所有 SubscribeRequest（包括 ping）
+    pub fn requests(&self) -> Vec<SubscribeRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    // 修改之后的 RPC 返回的链上状态，例如模拟区块高度增长
+    pub fn update_chain<F: FnOnce(&mut MockChain)>(&self, f: F) {
+        f(&mut self.state.chain.lock().unwrap());
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        self.task.abort();
+    }
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Geyser for MockGeyser {
+    type SubscribeStream = UpdateStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut requests = request.into_inner();
+        self.state.connections.fetch_add(1, Ordering::Relaxed);
+        let script = self.state.scripts.lock().unwrap().pop_front();
+        // None 表示结束流
+        let (tx, rx) =
+            mpsc::channel::<Option<Result<SubscribeUpdate, Status>>>(STREAM_CHANNEL_SIZE);
+
+        // 记录客户端请求，并像真实服务端一样回复客户端 ping
+        let state = self.state.clone();
+        let pong_tx = tx.clone();
+        let (first_request, first_request_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let mut first_request = Some(first_request);
+            while let Ok(Some(request)) = requests.message().await {
+                if let Some(ping) = &request.ping {
+                    let pong = Some(Ok(pong_update(ping.id)));
+                    let _ = pong_tx.send(pong).await;
+                }
+                state.requests.lock().unwrap().push(request);
+                if let Some(first_request) = first_request.take() {
+                    let _ = first_request.send(());
+                }
+            }
+        });
+
+        if let Some(steps) = script {
+            tokio::spawn(async move {
+                // 和真实服务端一样，收到订阅请求后才开始推送
+                let _ = first_request_rx.await;
+                for step in steps {
+                    let item = match step {
+                        MockStep::Update(update) => Some(Ok(update)),
+                        MockStep::Error(status) => Some(Err(status)),
+                        MockStep::Disconnect => None,
+                        MockStep::Sleep(duration) => {
+                            tokio::time::sleep(duration).await;
+                            continue;
+                        }
+                    };
+                    if tx.send(item).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        let stream = stream::unfold(rx, |mut rx| async move {
+            match rx.recv().await?? {
+                Ok(update) => Some((Ok(update), rx)),
+                // 错误之后流就结束了
+                Err(status) => {
+                    rx.close();
+                    Some((Err(status), rx))
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn subscribe_replay_info(
+        &self,
+        _request: Request<SubscribeReplayInfoRequest>,
+    ) -> Result<Response<SubscribeReplayInfoResponse>, Status> {
+        let chain = self.chain();
+        Ok(Response::new(SubscribeReplayInfoResponse {
+            first_available: chain.first_available,
+        }))
+    }
+
+    async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+        Ok(Response::new(PongResponse {
+            count: request.into_inner().count,
+        }))
+    }
+
+    async fn get_latest_blockhash(
+        &self,
+        _request: Request<GetLatestBlockhashRequest>,
+    ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+        let chain = self.chain();
+        Ok(Response::new(GetLatestBlockhashResponse {
+            slot: chain.slot,
+            blockhash: chain.blockhash.clone(),
+            last_valid_block_height: chain
+                .last_valid_block_height
+                .unwrap_or(chain.block_height + 150),
+        }))
+    }
+
+    async fn get_block_height(
+        &self,
+        _request: Request<GetBlockHeightRequest>,
+    ) -> Result<Response<GetBlockHeightResponse>, Status> {
+        let chain = self.chain();
+        Ok(Response::new(GetBlockHeightResponse {
+            block_height: chain.block_height,
+        }))
+    }
+
+    async fn get_slot(
+        &self,
+        _request: Request<GetSlotRequest>,
+    ) -> Result<Response<GetSlotResponse>, Status> {
+        let chain = self.chain();
+        Ok(Response::new(GetSlotResponse { slot: chain.slot }))
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        request: Request<IsBlockhashValidRequest>,
+    ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+        let chain = self.chain();
+        Ok(Response::new(IsBlockhashValidResponse {
+            slot: chain.slot,
+            valid: chain
+                .blockhash_valid
+                .unwrap_or(request.into_inner().blockhash == chain.blockhash),
+        }))
+    }
+
+    async fn get_version(
+        &self,
+        _request: Request<GetVersionRequest>,
+    ) -> Result<Response<GetVersionResponse>, Status> {
+        let chain = self.chain();
+        Ok(Response::new(GetVersionResponse {
+            version: chain.version.clone(),
+        }))
+    }
+}
+
+pub fn slot_update(slot: u64) -> SubscribeUpdate {
+    SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot,
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+pub fn ping_update() -> SubscribeUpdate {
+    SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+        ..Default::default()
+    }
+}
+
+pub fn pong_update(id: i32) -> SubscribeUpdate {
+    SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Pong(SubscribeUpdatePong { id })),
+        ..Default::default()
+    }
+}
+
+pub fn transaction_update(slot: u64, signature: &[u8], logs: Vec<String>) -> SubscribeUpdate {
+    SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: signature.to_vec(),
+                meta: Some(TransactionStatusMeta {
+                    log_messages: logs,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            slot,
+        })),
+        ..Default::default()
+    }
+}
+
+// 生成 "Program data: " 日志，内容为 discriminator + borsh 数据
+pub fn program_data_log(discriminator: [u8; 8], data: &[u8]) -> String {
+    let mut bytes = discriminator.to_vec();
+    bytes.extend_from_slice(data);
+    format!("Program data: {}", general_purpose::STANDARD.encode(bytes))
+}
@@ -0,0 +1,583 @@
+use borsh::BorshDeserialize;
+use log::debug;
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use yellowstone_grpc_proto::geyser::SubscribeUpdateTransaction;
+
+use crate::error::ClientError;
+
+pub const PUMP_PROGRAM: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+pub const PUMP_AMM_PROGRAM: Pubkey = pubkey!("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA");
+
+// Anchor 指令 discriminator：sha256("global:<name>") 的前 8 字节
+const CREATE: [u8; 8] = [24, 30, 200, 40, 5, 28, 7, 119];
+const BUY: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+const SELL: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+const WITHDRAW: [u8; 8] = [183, 18, 70, 156, 148, 109, 161, 34];
+const MIGRATE: [u8; 8] = [155, 234, 231, 146, 236, 158, 162, 30];
+const CREATE_POOL: [u8; 8] = [233, 146, 209, 142, 207, 104, 64, 188];
+const DEPOSIT: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+
+// 账户角色按 IDL 中的顺序；多出来的账户记为 remaining
+const PUMP_CREATE_ACCOUNTS: &[&str] = &[
+    "mint",
+    "mint_authority",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "global",
+    "mpl_token_metadata",
+    "metadata",
+    "user",
+    "system_program",
+    "token_program",
+    "associated_token_program",
+    "rent",
+    "event_authority",
+    "program",
+];
+const PUMP_BUY_ACCOUNTS: &[&str] = &[
+    "global",
+    "fee_recipient",
+    "mint",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "associated_user",
+    "user",
+    "system_program",
+    "token_program",
+    "creator_vault",
+    "event_authority",
+    "program",
+];
+const PUMP_SELL_ACCOUNTS: &[&str] = &[
+    "global",
+    "fee_recipient",
+    "mint",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "associated_user",
+    "user",
+    "system_program",
+    "creator_vault",
+    "token_program",
+    "event_authority",
+    "program",
+];
+const PUMP_WITHDRAW_ACCOUNTS: &[&str] = &[
+    "global",
+    "last_withdraw",
+    "mint",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "associated_user",
+    "user",
+    "system_program",
+    "token_program",
+    "rent",
+    "event_authority",
+    "program",
+];
+const PUMP_MIGRATE_ACCOUNTS: &[&str] = &[
+    "global",
+    "withdraw_authority",
+    "mint",
+    "bonding_curve",
+    "associated_bonding_curve",
+    "user",
+    "system_program",
+    "token_program",
+    "pump_amm",
+    "pool",
+    "pool_authority",
+    "pool_authority_mint_account",
+    "pool_authority_wsol_account",
+    "amm_global_config",
+    "wsol_mint",
+    "lp_mint",
+    "user_pool_token_account",
+    "pool_base_token_account",
+    "pool_quote_token_account",
+    "token_2022_program",
+    "associated_token_program",
+    "pump_amm_event_authority",
+    "event_authority",
+    "program",
+];
+const AMM_SWAP_ACCOUNTS: &[&str] = &[
+    "pool",
+    "user",
+    "global_config",
+    "base_mint",
+    "quote_mint",
+    "user_base_token_account",
+    "user_quote_token_account",
+    "pool_base_token_account",
+    "pool_quote_token_account",
+    "protocol_fee_recipient",
+    "protocol_fee_recipient_token_account",
+    "base_token_program",
+    "quote_token_program",
+    "system_program",
+    "associated_token_program",
+    "event_authority",
+    "program",
+    "coin_creator_vault_ata",
+    "coin_creator_vault_authority",
+];
+const AMM_CREATE_POOL_ACCOUNTS: &[&str] = &[
+    "pool",
+    "global_config",
+    "creator",
+    "base_mint",
+    "quote_mint",
+    "lp_mint",
+    "user_base_token_account",
+    "user_quote_token_account",
+    "user_pool_token_account",
+    "pool_base_token_account",
+    "pool_quote_token_account",
+    "system_program",
+    "token_2022_program",
+    "base_token_program",
+    "quote_token_program",
+    "associated_token_program",
+    "event_authority",
+    "program",
+];
+const AMM_LIQUIDITY_ACCOUNTS: &[&str] = &[
+    "pool",
+    "global_config",
+    "user",
+    "base_mint",
+    "quote_mint",
+    "lp_mint",
+    "user_base_token_account",
+    "user_quote_token_account",
+    "user_pool_token_account",
+    "pool_base_token_account",
+    "pool_quote_token_account",
+    "token_program",
+    "token_2022_program",
+    "event_authority",
+    "program",
+];
+
+pub const INSTRUCTION_TYPES: [&str; 10] = [
+    "pump_create_ix",
+    "pump_buy_ix",
+    "pump_sell_ix",
+    "pump_withdraw_ix",
+    "pump_migrate_ix",
+    "amm_buy_ix",
+    "amm_sell_ix",
+    "amm_create_pool_ix",
+    "amm_deposit_ix",
+    "amm_withdraw_ix",
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PumpCreateArgs {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    // 旧版本的 create 指令没有 creator 参数
+    pub creator: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PumpBuyArgs {
+    pub amount: u64,
+    pub max_sol_cost: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PumpSellArgs {
+    pub amount: u64,
+    pub min_sol_output: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AmmBuyArgs {
+    pub base_amount_out: u64,
+    pub max_quote_amount_in: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AmmSellArgs {
+    pub base_amount_in: u64,
+    pub min_quote_amount_out: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AmmCreatePoolArgs {
+    pub index: u16,
+    pub base_amount_in: u64,
+    pub quote_amount_in: u64,
+    pub coin_creator: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AmmDepositArgs {
+    pub lp_token_amount_out: u64,
+    pub max_base_amount_in: u64,
+    pub max_quote_amount_in: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AmmWithdrawArgs {
+    pub lp_token_amount_in: u64,
+    pub min_base_amount_out: u64,
+    pub min_quote_amount_out: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instruction {
+    PumpCreate(PumpCreateArgs),
+    PumpBuy(PumpBuyArgs),
+    PumpSell(PumpSellArgs),
+    PumpWithdraw,
+    PumpMigrate,
+    AmmBuy(AmmBuyArgs),
+    AmmSell(AmmSellArgs),
+    AmmCreatePool(AmmCreatePoolArgs),
+    AmmDeposit(AmmDepositArgs),
+    AmmWithdraw(AmmWithdrawArgs),
+}
+
+impl Instruction {
+    pub fn name(&self) -> &'static str {
+        let index = match self {
+            Instruction::PumpCreate(_) => 0,
+            Instruction::PumpBuy(_) => 1,
+            Instruction::PumpSell(_) => 2,
+            Instruction::PumpWithdraw => 3,
+            Instruction::PumpMigrate => 4,
+            Instruction::AmmBuy(_) => 5,
+            Instruction::AmmSell(_) => 6,
+            Instruction::AmmCreatePool(_) => 7,
+            Instruction::AmmDeposit(_) => 8,
+            Instruction::AmmWithdraw(_) => 9,
+        };
+        INSTRUCTION_TYPES[index]
+    }
+
+    pub fn account_roles(&self) -> &'static [&'static str] {
+        match self {
+            Instruction::PumpCreate(_) => PUMP_CREATE_ACCOUNTS,
+            Instruction::PumpBuy(_) => PUMP_BUY_ACCOUNTS,
+            Instruction::PumpSell(_) => PUMP_SELL_ACCOUNTS,
+            Instruction::PumpWithdraw => PUMP_WITHDRAW_ACCOUNTS,
+            Instruction::PumpMigrate => PUMP_MIGRATE_ACCOUNTS,
+            Instruction::AmmBuy(_) | Instruction::AmmSell(_) => AMM_SWAP_ACCOUNTS,
+            Instruction::AmmCreatePool(_) => AMM_CREATE_POOL_ACCOUNTS,
+            Instruction::AmmDeposit(_) | Instruction::AmmWithdraw(_) => AMM_LIQUIDITY_ACCOUNTS,
+        }
+    }
+
+    // 不是 Pump / Pump AMM 的指令或 discriminator 未知时返回 None
+    pub fn decode(program: &Pubkey, data: &[u8]) -> Result<Option<Self>, ClientError> {
+        let Some((discr, mut args)) = data.split_first_chunk::<8>() else {
+            return Ok(None);
+        };
+        let args = &mut args;
+        let instruction = if *program == PUMP_PROGRAM {
+            match *discr {
+                CREATE => Instruction::PumpCreate(PumpCreateArgs {
+                    name: read(args)?,
+                    symbol: read(args)?,
+                    uri: read(args)?,
+                    creator: read(args).ok(),
+                }),
+                BUY => Instruction::PumpBuy(PumpBuyArgs {
+                    amount: read(args)?,
+                    max_sol_cost: read(args)?,
+                }),
+                SELL => Instruction::PumpSell(PumpSellArgs {
+                    amount: read(args)?,
+                    min_sol_output: read(args)?,
+                }),
+                WITHDRAW => Instruction::PumpWithdraw,
+                MIGRATE => Instruction::PumpMigrate,
+                _ => return Ok(None),
+            }
+        } else if *program == PUMP_AMM_PROGRAM {
+            match *discr {
+                BUY => Instruction::AmmBuy(AmmBuyArgs {
+                    base_amount_out: read(args)?,
+                    max_quote_amount_in: read(args)?,
+                }),
+                SELL => Instruction::AmmSell(AmmSellArgs {
+                    base_amount_in: read(args)?,
+                    min_quote_amount_out: read(args)?,
+                }),
+                CREATE_POOL => Instruction::AmmCreatePool(AmmCreatePoolArgs {
+                    index: read(args)?,
+                    base_amount_in: read(args)?,
+                    quote_amount_in: read(args)?,
+                    coin_creator: read(args).ok(),
+                }),
+                DEPOSIT => Instruction::AmmDeposit(AmmDepositArgs {
+                    lp_token_amount_out: read(args)?,
+                    max_base_amount_in: read(args)?,
+                    max_quote_amount_in: read(args)?,
+                }),
+                WITHDRAW => Instruction::AmmWithdraw(AmmWithdrawArgs {
+                    lp_token_amount_in: read(args)?,
+                    min_base_amount_out: read(args)?,
+                    min_quote_amount_out: read(args)?,
+                }),
+                _ => return Ok(None),
+            }
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(instruction))
+    }
+}
+
+// 新版本可能在参数末尾追加字段，多余的字节忽略
+fn read<T: BorshDeserialize>(data: &mut &[u8]) -> Result<T, ClientError> {
+    T::deserialize(data).map_err(|e| ClientError::Decode(e.to_string()))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedInstruction {
+    pub program: Pubkey,
+    pub instruction: Instruction,
+    // (角色, 地址)
+    pub accounts: Vec<(&'static str, Pubkey)>,
+    // 所在顶层指令的序号；内部指令（CPI）另外记录在该指令中的序号
+    pub index: usize,
+    pub inner_index: Option<usize>,
+}
+
+impl DecodedInstruction {
+    pub fn account(&self, role: &str) -> Option<&Pubkey> {
+        self.accounts
+            .iter()
+            .find(|(name, _)| *name == role)
+            .map(|(_, pubkey)| pubkey)
+    }
+
+    pub fn accounts_summary(&self) -> String {
+        self.accounts
+            .iter()
+            .map(|(role, pubkey)| format!("{}={}", role, pubkey))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// 解析交易中所有 Pump / Pump AMM 指令，包括其他程序通过 CPI 调用的内部指令
+pub fn decode_transaction(transaction: &SubscribeUpdateTransaction) -> Vec<DecodedInstruction> {
+    let Some(info) = &transaction.transaction else {
+        return vec![];
+    };
+    let Some(message) = info.transaction.as_ref().and_then(|tx| tx.message.as_ref()) else {
+        return vec![];
+    };
+
+    // 地址表中加载的账户排在消息中的账户之后，先可写后只读
+    let mut keys = message.account_keys.clone();
+    if let Some(meta) = &info.meta {
+        keys.extend(meta.loaded_writable_addresses.iter().cloned());
+        keys.extend(meta.loaded_readonly_addresses.iter().cloned());
+    }
+    let keys: Vec<Option<Pubkey>> = keys
+        .iter()
+        .map(|key| Pubkey::try_from(key.as_slice()).ok())
+        .collect();
+
+    let inner = info
+        .meta
+        .as_ref()
+        .map(|meta| meta.inner_instructions.as_slice())
+        .unwrap_or_default();
+    let mut decoded = vec![];
+    for (index, instruction) in message.instructions.iter().enumerate() {
+        decoded.extend(decode_compiled(
+            &keys,
+            index,
+            None,
+            instruction.program_id_index,
+            &instruction.accounts,
+            &instruction.data,
+        ));
+        for group in inner.iter().filter(|group| group.index as usize == index) {
+            for (inner_index, instruction) in group.instructions.iter().enumerate() {
+                decoded.extend(decode_compiled(
+                    &keys,
+                    index,
+                    Some(inner_index),
+                    instruction.program_id_index,
+                    &instruction.accounts,
+                    &instruction.data,
+                ));
+            }
+        }
+    }
+    decoded
+}
+
+fn decode_compiled(
+    keys: &[Option<Pubkey>],
+    index: usize,
+    inner_index: Option<usize>,
+    program_id_index: u32,
+    accounts: &[u8],
+    data: &[u8],
+) -> Option<DecodedInstruction> {
+    let program = keys.get(program_id_index as usize).copied().flatten()?;
+    let instruction = match Instruction::decode(&program, data) {
+        Ok(instruction) => instruction?,
+        Err(e) => {
+            debug!(
+                "Failed to decode instruction {} of {}: {}",
+                index, program, e
+            );
+            return None;
+        }
+    };
+
+    let roles = instruction.account_roles();
+    let mut resolved = Vec::with_capacity(accounts.len());
+    for (i, &account) in accounts.iter().enumerate() {
+        let Some(pubkey) = keys.get(account as usize).copied().flatten() else {
+            debug!(
+                "Account index {} out of range for {} ({} keys)",
+                account,
+                instruction.name(),
+                keys.len()
+            );
+            return None;
+        };
+        resolved.push((roles.get(i).copied().unwrap_or("remaining"), pubkey));
+    }
+
+    Some(DecodedInstruction {
+        program,
+        instruction,
+        accounts: resolved,
+        index,
+        inner_index,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::hash;
+    use yellowstone_grpc_proto::{
+        geyser::SubscribeUpdateTransactionInfo,
+        prelude::{
+            CompiledInstruction, InnerInstruction, InnerInstructions, Message, Transaction,
+            TransactionStatusMeta,
+        },
+    };
+
+    #[test]
+    fn test_discriminators() {
+        for (name, discriminator) in [
+            ("create", CREATE),
+            ("buy", BUY),
+            ("sell", SELL),
+            ("withdraw", WITHDRAW),
+            ("migrate", MIGRATE),
+            ("create_pool", CREATE_POOL),
+            ("deposit", DEPOSIT),
+        ] {
+            let digest = hash(format!("global:{}", name).as_bytes());
+            assert_eq!(digest.to_bytes()[..8], discriminator, "{}", name);
+        }
+    }
+
+    fn data(discriminator: [u8; 8], args: &[u64]) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        for arg in args {
+            data.extend(arg.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_decode_with_accounts() {
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let router = Pubkey::new_unique();
+        let others: Vec<Pubkey> = (0..9).map(|_| Pubkey::new_unique()).collect();
+
+        // 顶层是路由程序，Pump buy 通过 CPI 调用；program 账户来自地址表
+        let keys: Vec<Vec<u8>> = [user, router]
+            .iter()
+            .chain(&others)
+            .map(|key| key.to_bytes().to_vec())
+            .collect();
+        let buy_accounts: Vec<u8> = vec![2, 3, 11, 4, 5, 6, 0, 7, 8, 9, 10, 12];
+        let update = SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                transaction: Some(Transaction {
+                    message: Some(Message {
+                        account_keys: keys,
+                        instructions: vec![CompiledInstruction {
+                            program_id_index: 1,
+                            accounts: vec![0],
+                            data: vec![1, 2, 3],
+                        }],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                meta: Some(TransactionStatusMeta {
+                    loaded_readonly_addresses: vec![
+                        mint.to_bytes().to_vec(),
+                        PUMP_PROGRAM.to_bytes().to_vec(),
+                    ],
+                    inner_instructions: vec![InnerInstructions {
+                        index: 0,
+                        instructions: vec![InnerInstruction {
+                            program_id_index: 12,
+                            accounts: buy_accounts,
+                            data: data(BUY, &[1_000_000, 50_000_000, 1]),
+                            stack_height: Some(2),
+                        }],
+                    }],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let decoded = decode_transaction(&update);
+        assert_eq!(decoded.len(), 1);
+        let buy = &decoded[0];
+        assert_eq!(
+            buy.instruction,
+            Instruction::PumpBuy(PumpBuyArgs {
+                amount: 1_000_000,
+                max_sol_cost: 50_000_000,
+            })
+        );
+        assert_eq!((buy.index, buy.inner_index), (0, Some(0)));
+        assert_eq!(buy.account("user"), Some(&user));
+        assert_eq!(buy.account("mint"), Some(&mint));
+        assert_eq!(buy.account("program"), Some(&PUMP_PROGRAM));
+    }
+
+    #[test]
+    fn test_decode_amm_and_unknown() {
+        let deposit = Instruction::decode(&PUMP_AMM_PROGRAM, &data(DEPOSIT, &[1, 2, 3]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(deposit.name(), "amm_deposit_ix");
+
+        // 同一个 discriminator 在不同程序中对应不同指令
+        let sell = Instruction::decode(&PUMP_AMM_PROGRAM, &data(SELL, &[5, 6])).unwrap();
+        assert!(matches!(sell, Some(Instruction::AmmSell(_))));
+        assert!(
+            Instruction::decode(&Pubkey::new_unique(), &data(SELL, &[5, 6]))
+                .unwrap()
+                .is_none()
+        );
+        assert!(Instruction::decode(&PUMP_PROGRAM, &data(SELL, &[5])).is_err());
+    }
+}
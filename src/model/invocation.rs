@@ -0,0 +1,187 @@
+// 根据 "Program <id> invoke [n]" / "success" / "failed" 日志还原调用树，
+// 用来确定每一行日志是哪个程序输出的
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvocationStatus {
+    // 日志被截断或交易中途失败时没有结束行
+    Unfinished,
+    Success,
+    Failed(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invocation {
+    pub program: String,
+    // 1 为交易直接调用的指令
+    pub depth: usize,
+    // 父调用在 invocations() 中的下标
+    pub parent: Option<usize>,
+    pub status: InvocationStatus,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InvocationTree {
+    invocations: Vec<Invocation>,
+    // 每行日志所属的调用
+    owners: Vec<Option<usize>>,
+}
+
+enum Line<'a> {
+    Invoke {
+        program: &'a str,
+        depth: usize,
+    },
+    Exit {
+        program: &'a str,
+        status: InvocationStatus,
+    },
+}
+
+impl InvocationTree {
+    pub fn parse(logs: &[String]) -> Self {
+        let mut tree = Self::default();
+        let mut stack: Vec<usize> = vec![];
+        for log in logs {
+            let owner = match parse_line(log) {
+                Some(Line::Invoke { program, depth }) => {
+                    // 按 depth 恢复调用栈，防止漏掉的结束行让后面的调用挂错父节点
+                    stack.truncate(depth.saturating_sub(1));
+                    let index = tree.invocations.len();
+                    tree.invocations.push(Invocation {
+                        program: program.to_string(),
+                        depth,
+                        parent: stack.last().copied(),
+                        status: InvocationStatus::Unfinished,
+                    });
+                    stack.push(index);
+                    Some(index)
+                }
+                Some(Line::Exit { program, status }) => {
+                    match stack
+                        .iter()
+                        .rposition(|&index| tree.invocations[index].program == program)
+                    {
+                        Some(position) => {
+                            let index = stack[position];
+                            stack.truncate(position);
+                            tree.invocations[index].status = status;
+                            Some(index)
+                        }
+                        None => stack.last().copied(),
+                    }
+                }
+                None => stack.last().copied(),
+            };
+            tree.owners.push(owner);
+        }
+        tree
+    }
+
+    pub fn invocations(&self) -> &[Invocation] {
+        &self.invocations
+    }
+
+    // 输出第 log_index 行日志的调用
+    pub fn emitter(&self, log_index: usize) -> Option<&Invocation> {
+        let index = (*self.owners.get(log_index)?)?;
+        self.invocations.get(index)
+    }
+
+    pub fn parent(&self, invocation: &Invocation) -> Option<&Invocation> {
+        self.invocations.get(invocation.parent?)
+    }
+
+    // 自己或任一上层调用失败时，这次调用的状态修改都会回滚
+    pub fn failed(&self, invocation: &Invocation) -> bool {
+        let mut current = Some(invocation);
+        while let Some(invocation) = current {
+            if matches!(invocation.status, InvocationStatus::Failed(_)) {
+                return true;
+            }
+            current = self.parent(invocation);
+        }
+        false
+    }
+}
+
+fn parse_line(log: &str) -> Option<Line<'_>> {
+    let rest = log.strip_prefix("Program ")?;
+    // Program log: / Program data: / Program return: 是程序自己的输出
+    if ["log:", "data:", "return:"]
+        .iter()
+        .any(|prefix| rest.starts_with(prefix))
+    {
+        return None;
+    }
+    let (program, rest) = rest.split_once(' ')?;
+    if let Some(depth) = rest.strip_prefix("invoke [") {
+        let depth = depth.strip_suffix(']')?.parse().ok()?;
+        return Some(Line::Invoke { program, depth });
+    }
+    let status = if rest == "success" {
+        InvocationStatus::Success
+    } else {
+        let error = rest.strip_prefix("failed")?;
+        InvocationStatus::Failed(error.trim_start_matches(':').trim().to_string())
+    };
+    Some(Line::Exit { program, status })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invocation_tree() {
+        let logs: Vec<String> = [
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]",
+            "Program log: Instruction: Route",
+            "Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA invoke [2]",
+            "Program data: AAAA",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [3]",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
+            "Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA consumed 5000 of 190000 compute units",
+            "Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA success",
+            "Program data: BBBB",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 failed: custom program error: 0x1",
+        ]
+        .map(String::from)
+        .to_vec();
+        let tree = InvocationTree::parse(&logs);
+        assert_eq!(tree.invocations().len(), 3);
+
+        let amm = tree.emitter(3).unwrap();
+        assert_eq!(amm.program, "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA");
+        assert_eq!(amm.depth, 2);
+        assert_eq!(amm.status, InvocationStatus::Success);
+        assert_eq!(
+            tree.parent(amm).unwrap().program,
+            "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"
+        );
+
+        // 内层调用返回后的日志属于外层程序
+        let jupiter = tree.emitter(8).unwrap();
+        assert_eq!(jupiter.depth, 1);
+        assert_eq!(
+            jupiter.status,
+            InvocationStatus::Failed("custom program error: 0x1".to_string())
+        );
+        assert!(tree.parent(jupiter).is_none());
+        assert_eq!(tree.invocations()[2].parent, Some(1));
+
+        // 外层 Jupiter 失败，内层成功的调用同样被回滚
+        assert!(tree.failed(jupiter));
+        assert!(tree.failed(amm));
+        let token = &tree.invocations()[2];
+        assert!(tree.failed(token));
+        let logs = vec![logs[0].clone(), logs[1].clone()];
+        let tree = InvocationTree::parse(&logs);
+        assert!(!tree.failed(tree.emitter(1).unwrap()));
+    }
+
+    #[test]
+    fn test_logs_without_invoke() {
+        let logs = vec!["Program data: AAAA".to_string()];
+        assert!(InvocationTree::parse(&logs).emitter(0).is_none());
+    }
+}
@@ -1,35 +1,236 @@
-use std::error::Error;
-
 use base64::{Engine, engine::general_purpose};
 
+use crate::error::ClientError;
+use invocation::InvocationTree;
+
+pub mod instruction;
+pub mod invocation;
 pub mod pumpamm;
 pub mod pumpfun_model;
 
-const PROGRAM_DATA: &str = "Program data: ";
+pub(crate) const PROGRAM_DATA: &str = "Program data: ";
+
+pub use grpc_jh_derive::SolanaEvent;
+
+// 从日志中解析出的事件，log_index 是 "Program data: " 行在日志中的下标
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogEvent<T> {
+    pub log_index: usize,
+    // 输出这行日志的程序和调用深度（1 为顶层指令），没有 invoke 日志时为 None / 0
+    pub program: Option<String>,
+    pub depth: usize,
+    // 通过 CPI 调用时的上层程序，例如 Jupiter -> Pump AMM
+    pub parent: Option<String>,
+    pub event: T,
+}
 
+// 一般通过 #[derive(SolanaEvent)] 实现
 pub trait EventTrait: Sized + std::fmt::Debug {
-    fn discriminator() -> [u8; 8];
+    // 输出和统计里使用的事件类型，例如 pump_trade
+    const NAME: &'static str;
+    // 发出这个事件的程序
+    const PROGRAM: Option<&'static str>;
+    const DISCRIMINATOR: [u8; 8];
+
+    fn discriminator() -> [u8; 8] {
+        Self::DISCRIMINATOR
+    }
 
-    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>>;
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ClientError>;
 
-    fn valid_discrminator(head: &[u8]) -> bool;
+    fn valid_discrminator(head: &[u8]) -> bool {
+        head == Self::DISCRIMINATOR
+    }
 
+    // 最后一个 T 事件
     fn parse_logs<T: EventTrait + Clone>(logs: &[String]) -> Option<T> {
-        // println!("{:#?}", logs);
-        // std::process::exit(1);
-        logs.iter().rev().find_map(|log| {
-            let payload = log.strip_prefix(PROGRAM_DATA)?;
-            let bytes = general_purpose::STANDARD
-                .decode(payload)
-                .map_err(|e| Box::new(e) as Box<dyn Error>)
-                .ok()?;
-
-            let (discr, rest) = bytes.split_at(8);
-            if Self::valid_discrminator(discr) {
-                T::from_bytes(rest).ok()
-            } else {
-                None
+        T::parse_all_logs(logs)
+            .pop()
+            .map(|log_event| log_event.event)
+    }
+
+    // 按日志顺序返回所有匹配 Self 的事件；设置了 PROGRAM 时只接受该程序输出的日志，
+    // 防止其他程序伪造相同 discriminator 的数据
+    fn parse_all_logs(logs: &[String]) -> Vec<LogEvent<Self>> {
+        let tree = InvocationTree::parse(logs);
+        Self::parse_logs_from(logs, &tree, Self::PROGRAM.as_slice())
+    }
+
+    // 只接受 programs 中的程序输出的事件（例如 fork 或 devnet 部署的程序 ID），
+    // programs 为空时不检查；失败（或上层调用失败）的调用输出的事件会被跳过
+    fn parse_logs_from<S: AsRef<str>>(
+        logs: &[String],
+        tree: &InvocationTree,
+        programs: &[S],
+    ) -> Vec<LogEvent<Self>> {
+        logs.iter()
+            .enumerate()
+            .filter_map(|(log_index, log)| {
+                let payload = log.strip_prefix(PROGRAM_DATA)?;
+                let bytes = general_purpose::STANDARD.decode(payload).ok()?;
+
+                let (discr, rest) = bytes.split_at_checked(8)?;
+                if !Self::valid_discrminator(discr) {
+                    return None;
+                }
+                let emitter = tree.emitter(log_index);
+                // 失败的调用输出的事件没有实际发生
+                if emitter.is_some_and(|emitter| tree.failed(emitter)) {
+                    return None;
+                }
+                if !programs.is_empty()
+                    && emitter.is_none_or(|emitter| {
+                        !programs
+                            .iter()
+                            .any(|program| program.as_ref() == emitter.program)
+                    })
+                {
+                    return None;
+                }
+                let event = Self::from_bytes(rest).ok()?;
+                Some(LogEvent {
+                    log_index,
+                    program: emitter.map(|emitter| emitter.program.clone()),
+                    depth: emitter.map_or(0, |emitter| emitter.depth),
+                    parent: emitter
+                        .and_then(|emitter| tree.parent(emitter))
+                        .map(|parent| parent.program.clone()),
+                    event,
+                })
+            })
+            .collect()
+    }
+}
+
+// 在 crate 根调用一次，定义 #[derive(SolanaEvent)] 用来检查 discriminator 冲突的类型。
+// 每个事件生成 `impl Registered for Discriminator<D>`，两个事件的 discriminator 相同时
+// impl 冲突，编译不通过；类型必须定义在使用 derive 的 crate 中，否则违反孤儿规则
+#[macro_export]
+macro_rules! solana_events {
+    () => {
+        // 只用于编译期的 impl 冲突检查
+        #[doc(hidden)]
+        #[allow(dead_code)]
+        pub(crate) mod __solana_events {
+            pub(crate) struct Discriminator<const D: u64>;
+
+            pub(crate) trait Registered {
+                type Event;
             }
-        })
+        }
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use borsh::BorshDeserialize;
+    pub use inventory;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pumpamm::{BuyEvent, CreatePoolEvent, SellEvent};
+    use pumpfun_model::{CompleteEvent, CreateEvent, TradeEvent};
+
+    #[test]
+    fn test_derived_discriminators() {
+        // 原来手写的 discriminator
+        assert_eq!(
+            CreateEvent::DISCRIMINATOR,
+            [27, 114, 169, 77, 222, 235, 99, 118]
+        );
+        assert_eq!(
+            CompleteEvent::DISCRIMINATOR,
+            [95, 114, 97, 156, 212, 46, 152, 8]
+        );
+        assert_eq!(
+            TradeEvent::DISCRIMINATOR,
+            [189, 219, 127, 211, 78, 230, 97, 238]
+        );
+        assert_eq!(
+            BuyEvent::DISCRIMINATOR,
+            [103, 244, 82, 31, 44, 245, 119, 119]
+        );
+        assert_eq!(SellEvent::DISCRIMINATOR, [62, 47, 55, 10, 165, 3, 220, 42]);
+        assert_eq!(
+            CreatePoolEvent::DISCRIMINATOR,
+            [177, 49, 12, 210, 160, 118, 167, 116]
+        );
+
+        assert_eq!(TradeEvent::NAME, "pump_trade");
+        assert_eq!(
+            BuyEvent::PROGRAM,
+            Some("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA")
+        );
+    }
+
+    fn program_data<T: EventTrait + borsh::BorshSerialize>(event: &T) -> String {
+        let mut bytes = T::DISCRIMINATOR.to_vec();
+        event.serialize(&mut bytes).unwrap();
+        format!(
+            "{}{}",
+            PROGRAM_DATA,
+            general_purpose::STANDARD.encode(bytes)
+        )
+    }
+
+    #[test]
+    fn test_parse_all_logs() {
+        let first = TradeEvent {
+            sol_amount: 1,
+            ..Default::default()
+        };
+        let second = TradeEvent {
+            sol_amount: 2,
+            ..Default::default()
+        };
+        let logs = vec![
+            "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P invoke [1]".to_string(),
+            program_data(&first),
+            program_data(&CompleteEvent::default()),
+            program_data(&second),
+        ];
+
+        let trades: Vec<_> = TradeEvent::parse_all_logs(&logs)
+            .into_iter()
+            .map(|log_event| (log_event.log_index, log_event.event))
+            .collect();
+        assert_eq!(trades, vec![(1, first), (3, second)]);
+        assert_eq!(CompleteEvent::parse_all_logs(&logs)[0].log_index, 2);
+        assert!(CreateEvent::parse_all_logs(&logs).is_empty());
+    }
+
+    #[test]
+    fn test_events_attributed_to_program() {
+        let logs = vec![
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]".to_string(),
+            "Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA invoke [2]".to_string(),
+            program_data(&BuyEvent::default()),
+            "Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA success".to_string(),
+            // 其他程序输出的同 discriminator 数据不应被接受
+            program_data(&BuyEvent::default()),
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 success".to_string(),
+            program_data(&TradeEvent::default()),
+        ];
+
+        let buys = BuyEvent::parse_all_logs(&logs);
+        assert_eq!(buys.len(), 1);
+        assert_eq!(buys[0].log_index, 2);
+        assert_eq!(buys[0].program.as_deref(), BuyEvent::PROGRAM);
+        assert_eq!(buys[0].depth, 2);
+        assert_eq!(
+            buys[0].parent.as_deref(),
+            Some("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4")
+        );
+        assert!(TradeEvent::parse_all_logs(&logs).is_empty());
+
+        let failed = vec![
+            "Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA invoke [1]".to_string(),
+            program_data(&BuyEvent::default()),
+            "Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA failed: custom program error: 0x1772"
+                .to_string(),
+        ];
+        assert!(BuyEvent::parse_all_logs(&failed).is_empty());
     }
 }
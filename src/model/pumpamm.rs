@@ -1,12 +1,13 @@
-use base64::Engine;
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_sdk::pubkey::Pubkey;
 
-use crate::model::EventTrait;
+use crate::model::SolanaEvent;
 
-use std::error::Error;
-
-#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, SolanaEvent)]
+#[event(
+    name = "amm_buy",
+    program = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA"
+)]
 pub struct BuyEvent {
     pub timestamp: i64,
     pub base_amount_out: u64,
@@ -33,7 +34,11 @@ pub struct BuyEvent {
     pub coin_creator_fee: u64,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, SolanaEvent)]
+#[event(
+    name = "amm_sell",
+    program = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA"
+)]
 pub struct SellEvent {
     pub timestamp: i64,
     pub base_amount_in: u64,
@@ -60,7 +65,11 @@ pub struct SellEvent {
     pub coin_creator_fee: u64,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, SolanaEvent)]
+#[event(
+    name = "amm_create_pool",
+    program = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA"
+)]
 pub struct CreatePoolEvent {
     pub timestamp: i64,
     pub index: u16,
@@ -83,83 +92,3 @@ pub struct CreatePoolEvent {
     pub user_quote_token_account: Pubkey,
     pub coin_creator: Pubkey,
 }
-
-impl EventTrait for BuyEvent {
-    fn discriminator() -> [u8; 8] {
-        [103, 244, 82, 31, 44, 245, 119, 119]
-    }
-
-    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
-        Self::try_from_slice(bytes).map_err(|e| Box::new(e) as Box<dyn Error>)
-    }
-
-    fn valid_discrminator(discr: &[u8]) -> bool {
-        discr == Self::discriminator()
-    }
-
-    fn parse_logs<T: EventTrait + Clone>(logs: &[String]) -> Option<T> {
-        // println!("{:#?}", logs);
-        // std::process::exit(1);
-        logs.iter().rev().find_map(|log| {
-            let payload = log.strip_prefix(super::PROGRAM_DATA)?;
-            let bytes = base64::engine::general_purpose::STANDARD
-                .decode(payload)
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
-                .ok()?;
-
-            let (discr, rest) = bytes.split_at(8);
-            if Self::valid_discrminator(discr) {
-                T::from_bytes(rest).ok()
-            } else {
-                None
-            }
-        })
-    }
-}
-
-impl EventTrait for CreatePoolEvent {
-    fn discriminator() -> [u8; 8] {
-        [177, 49, 12, 210, 160, 118, 167, 116]
-    }
-
-    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
-        Self::try_from_slice(bytes).map_err(|e| Box::new(e) as Box<dyn Error>)
-    }
-
-    fn valid_discrminator(discr: &[u8]) -> bool {
-        discr == Self::discriminator()
-    }
-
-    fn parse_logs<T: EventTrait + Clone>(logs: &[String]) -> Option<T> {
-        // println!("{:#?}", logs);
-        // std::process::exit(1);
-        logs.iter().rev().find_map(|log| {
-            let payload = log.strip_prefix(super::PROGRAM_DATA)?;
-            let bytes = base64::engine::general_purpose::STANDARD
-                .decode(payload)
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
-                .ok()?;
-
-            let (discr, rest) = bytes.split_at(8);
-            if Self::valid_discrminator(discr) {
-                T::from_bytes(rest).ok()
-            } else {
-                None
-            }
-        })
-    }
-}
-
-impl EventTrait for SellEvent {
-    fn discriminator() -> [u8; 8] {
-        [62, 47, 55, 10, 165, 3, 220, 42]
-    }
-
-    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
-        Self::try_from_slice(bytes).map_err(|e| Box::new(e) as Box<dyn Error>)
-    }
-
-    fn valid_discrminator(discr: &[u8]) -> bool {
-        discr == Self::discriminator()
-    }
-}
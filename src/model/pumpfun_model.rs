@@ -1,12 +1,13 @@
-use std::error::Error;
-
-use base64::Engine;
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_sdk::pubkey::Pubkey;
 
-use crate::model::EventTrait;
+use crate::model::SolanaEvent;
 
-#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, SolanaEvent)]
+#[event(
+    name = "pump_create",
+    program = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"
+)]
 pub struct CreateEvent {
     pub name: String,
     pub symbol: String,
@@ -22,7 +23,11 @@ pub struct CreateEvent {
     pub token_total_supply: u64,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, SolanaEvent)]
+#[event(
+    name = "pump_complete",
+    program = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"
+)]
 pub struct CompleteEvent {
     pub user: Pubkey,
     pub mint: Pubkey,
@@ -30,7 +35,11 @@ pub struct CompleteEvent {
     pub timestamp: i64,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, SolanaEvent)]
+#[event(
+    name = "pump_trade",
+    program = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"
+)]
 pub struct TradeEvent {
     pub mint: Pubkey,
     pub sol_amount: u64,
@@ -49,64 +58,3 @@ pub struct TradeEvent {
     pub creator_fee_basis_points: u64,
     pub creator_fee: u64,
 }
-
-impl EventTrait for CreateEvent {
-    fn discriminator() -> [u8; 8] {
-        [27, 114, 169, 77, 222, 235, 99, 118]
-    }
-
-    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
-        Self::try_from_slice(bytes).map_err(|e| Box::new(e) as Box<dyn Error>)
-    }
-
-    fn valid_discrminator(discr: &[u8]) -> bool {
-        discr == Self::discriminator()
-    }
-
-    fn parse_logs<T: EventTrait + Clone>(logs: &[String]) -> Option<T> {
-        // println!("{:#?}", logs);
-        // std::process::exit(1);
-        logs.iter().rev().find_map(|log| {
-            let payload = log.strip_prefix(super::PROGRAM_DATA)?;
-            let bytes = base64::engine::general_purpose::STANDARD
-                .decode(payload)
-                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
-                .ok()?;
-
-            let (discr, rest) = bytes.split_at(8);
-            if Self::valid_discrminator(discr) {
-                T::from_bytes(rest).ok()
-            } else {
-                None
-            }
-        })
-    }
-}
-
-impl EventTrait for CompleteEvent {
-    fn discriminator() -> [u8; 8] {
-        [95, 114, 97, 156, 212, 46, 152, 8]
-    }
-
-    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
-        Self::try_from_slice(bytes).map_err(|e| Box::new(e) as Box<dyn Error>)
-    }
-
-    fn valid_discrminator(discr: &[u8]) -> bool {
-        discr == Self::discriminator()
-    }
-}
-
-impl EventTrait for TradeEvent {
-    fn discriminator() -> [u8; 8] {
-        [189, 219, 127, 211, 78, 230, 97, 238]
-    }
-
-    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
-        Self::try_from_slice(bytes).map_err(|e| Box::new(e) as Box<dyn Error>)
-    }
-
-    fn valid_discrminator(discr: &[u8]) -> bool {
-        discr == Self::discriminator()
-    }
-}
@@ -0,0 +1,295 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex as StdMutex},
+    time::{Duration, Instant},
+};
+
+use log::{error, info};
+use tokio::sync::{Mutex, mpsc};
+use yellowstone_grpc_proto::geyser::{
+    SubscribeRequest, SubscribeUpdateTransaction, subscribe_update::UpdateOneof,
+};
+
+use crate::{
+    engine::UpdateDispatcher, error::ClientError, grpc::YellowstoneGrpc, handle::EventHandler,
+};
+
+const CHANNEL_SIZE: usize = 4096;
+const DEFAULT_DEDUP_CAPACITY: usize = 100_000;
+const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Default)]
+pub struct EndpointStats {
+    pub endpoint: String,
+    pub received: u64,
+    // 最先送达的次数
+    pub wins: u64,
+    pub duplicates: u64,
+    pub total_lag: Duration,
+    pub max_lag: Duration,
+}
+
+impl EndpointStats {
+    // 落后于最先送达者的平均时间
+    pub fn avg_lag(&self) -> Duration {
+        if self.duplicates == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total_lag.as_nanos() / self.duplicates as u128) as u64)
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.received == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.received as f64
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Delivery {
+    First,
+    Duplicate { winner: usize, lag: Duration },
+}
+
+// 按签名去重，超过容量后淘汰最早的签名
+pub struct SignatureDedup {
+    capacity: usize,
+    seen: HashMap<Vec<u8>, (usize, Instant)>,
+    order: VecDeque<Vec<u8>>,
+}
+
+impl SignatureDedup {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn observe(&mut self, signature: &[u8], endpoint: usize, now: Instant) -> Delivery {
+        if let Some((winner, first_seen)) = self.seen.get(signature) {
+            return Delivery::Duplicate {
+                winner: *winner,
+                lag: now.saturating_duration_since(*first_seen),
+            };
+        }
+
+        if self.order.len() >= self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.seen.remove(&oldest);
+        }
+        self.seen.insert(signature.to_vec(), (endpoint, now));
+        self.order.push_back(signature.to_vec());
+        Delivery::First
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+// 同时订阅多个端点，合并交易流并按签名去重
+pub struct MultiSubscriber {
+    clients: Vec<YellowstoneGrpc>,
+    pub event_handler: Arc<Mutex<EventHandler>>,
+    stats: Arc<StdMutex<Vec<EndpointStats>>>,
+    dedup_capacity: usize,
+    report_interval: Duration,
+}
+
+impl MultiSubscriber {
+    pub fn new(clients: Vec<YellowstoneGrpc>) -> Self {
+        let stats = clients
+            .iter()
+            .map(|client| EndpointStats {
+                endpoint: client.endpoint().to_string(),
+                ..Default::default()
+            })
+            .collect();
+        Self {
+            clients,
+            event_handler: Arc::new(Mutex::new(EventHandler::new())),
+            stats: Arc::new(StdMutex::new(stats)),
+            dedup_capacity: DEFAULT_DEDUP_CAPACITY,
+            report_interval: DEFAULT_REPORT_INTERVAL,
+        }
+    }
+
+    pub fn with_dedup_capacity(mut self, capacity: usize) -> Self {
+        self.dedup_capacity = capacity;
+        self
+    }
+
+    pub fn with_report_interval(mut self, interval: Duration) -> Self {
+        self.report_interval = interval;
+        self
+    }
+
+    pub fn stats(&self) -> Vec<EndpointStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub async fn subscribe(&self, request: SubscribeRequest) -> Result<(), ClientError> {
+        if self.clients.is_empty() {
+            return Err(ClientError::Config("no endpoints configured".to_string()));
+        }
+
+        // 到达时间在各端点的任务中记录，不受处理端排队影响
+        let (tx, mut rx) =
+            mpsc::channel::<(usize, Instant, SubscribeUpdateTransaction)>(CHANNEL_SIZE);
+        for (index, client) in self.clients.iter().cloned().enumerate() {
+            let tx = tx.clone();
+            let request = request.clone();
+            tokio::spawn(async move {
+                let endpoint = client.endpoint().to_string();
+                let result = client
+                    .subscribe_supervised(request, move |msg| {
+                        let tx = tx.clone();
+                        async move {
+                            if let Some(UpdateOneof::Transaction(sut)) = msg.update_oneof {
+                                tx.send((index, Instant::now(), sut))
+                                    .await
+                                    .map_err(|e| ClientError::Handler(e.to_string()))?;
+                            }
+                            Ok(())
+                        }
+                    })
+                    .await;
+                if let Err(e) = result {
+                    error!("Endpoint {} stopped: {:?}", endpoint, e);
+                }
+            });
+        }
+        drop(tx);
+
+        let mut dedup = SignatureDedup::new(self.dedup_capacity);
+        let mut report = tokio::time::interval(self.report_interval);
+        report.tick().await;
+
+        loop {
+            tokio::select! {
+                item = rx.recv() => {
+                    let Some((index, received_at, sut)) = item else {
+                        break;
+                    };
+                    self.on_transaction(&mut dedup, index, received_at, sut).await;
+                }
+                _ = report.tick() => self.log_stats(),
+            }
+        }
+
+        // 所有端点都已停止（例如收到退出信号）
+        self.log_stats();
+        if let Err(e) = self.event_handler.lock().await.flush().await {
+            error!("Failed to flush event handler: {:?}", e);
+        }
+        Ok(())
+    }
+
+    async fn on_transaction(
+        &self,
+        dedup: &mut SignatureDedup,
+        index: usize,
+        received_at: Instant,
+        sut: SubscribeUpdateTransaction,
+    ) {
+        let Some(signature) = sut.transaction.as_ref().map(|tx| tx.signature.clone()) else {
+            return;
+        };
+        let delivery = dedup.observe(&signature, index, received_at);
+
+        {
+            let mut stats = self.stats.lock().unwrap();
+            let endpoint = &mut stats[index];
+            endpoint.received += 1;
+            match delivery {
+                Delivery::First => endpoint.wins += 1,
+                Delivery::Duplicate { winner, lag } => {
+                    // 同一端点重连回放的重复数据不计入延迟
+                    if winner != index {
+                        endpoint.duplicates += 1;
+                        endpoint.total_lag += lag;
+                        endpoint.max_lag = endpoint.max_lag.max(lag);
+                    }
+                }
+            }
+        }
+
+        if delivery == Delivery::First
+            && let Err(e) = self
+                .event_handler
+                .lock()
+                .await
+                .handle_transaction(sut)
+                .await
+        {
+            error!("Error handling transaction: {:?}", e);
+        }
+    }
+
+    fn log_stats(&self) {
+        for stats in self.stats() {
+            info!(
+                "endpoint: {}, received: {}, wins: {} ({:.1}%), avg lag: {:?}, max lag: {:?}",
+                stats.endpoint,
+                stats.received,
+                stats.wins,
+                stats.win_rate() * 100.0,
+                stats.avg_lag(),
+                stats.max_lag
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_delivery_wins() {
+        let mut dedup = SignatureDedup::new(16);
+        let now = Instant::now();
+        assert_eq!(dedup.observe(b"sig", 1, now), Delivery::First);
+        assert_eq!(
+            dedup.observe(b"sig", 0, now + Duration::from_millis(5)),
+            Delivery::Duplicate {
+                winner: 1,
+                lag: Duration::from_millis(5)
+            }
+        );
+    }
+
+    #[test]
+    fn test_dedup_evicts_oldest() {
+        let mut dedup = SignatureDedup::new(2);
+        let now = Instant::now();
+        dedup.observe(b"a", 0, now);
+        dedup.observe(b"b", 0, now);
+        dedup.observe(b"c", 0, now);
+        assert_eq!(dedup.len(), 2);
+        assert_eq!(dedup.observe(b"a", 1, now), Delivery::First);
+    }
+
+    #[test]
+    fn test_avg_lag_with_many_duplicates() {
+        let stats = EndpointStats {
+            endpoint: "a".to_string(),
+            received: 0,
+            wins: 0,
+            duplicates: 1 << 32,
+            total_lag: Duration::from_millis(2 << 32),
+            max_lag: Duration::ZERO,
+        };
+        assert_eq!(stats.avg_lag(), Duration::from_millis(2));
+    }
+}
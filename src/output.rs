@@ -0,0 +1,439 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use solana_sdk::bs58;
+use yellowstone_grpc_proto::geyser::{SubscribeUpdateAccount, SubscribeUpdateTransaction};
+
+use crate::{
+    engine::{DispatchResult, UpdateContext, UpdateDispatcher},
+    error::ClientError,
+    handle::{EventHandler, TxEvent},
+    model::instruction::decode_transaction,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Pretty,
+    // 每行一个 JSON 对象
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(ClientError::Config(format!(
+                "unknown output format {} (expected pretty, json or csv)",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pretty => write!(f, "pretty"),
+            Self::Json => write!(f, "json"),
+            Self::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+// 按行输出键值对；CSV 的表头取第一行的键
+pub struct OutputWriter<W> {
+    format: OutputFormat,
+    writer: W,
+    header_written: bool,
+}
+
+impl OutputWriter<io::Stdout> {
+    pub fn stdout(format: OutputFormat) -> Self {
+        Self::new(format, io::stdout())
+    }
+}
+
+impl<W: Write> OutputWriter<W> {
+    pub fn new(format: OutputFormat, writer: W) -> Self {
+        Self {
+            format,
+            writer,
+            header_written: false,
+        }
+    }
+
+    pub fn write_row(&mut self, fields: &[(&str, String)]) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Pretty => {
+                writeln!(
+                    self.writer,
+                    "-----------------------------------------------"
+                )?;
+                for (key, value) in fields {
+                    writeln!(self.writer, "{}: {}", key, value)?;
+                }
+            }
+            OutputFormat::Json => {
+                let object: serde_json::Map<String, serde_json::Value> = fields
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.clone().into()))
+                    .collect();
+                writeln!(self.writer, "{}", serde_json::Value::Object(object))?;
+            }
+            OutputFormat::Csv => {
+                if !self.header_written {
+                    let header: Vec<_> = fields.iter().map(|(key, _)| csv_field(key)).collect();
+                    writeln!(self.writer, "{}", header.join(","))?;
+                    self.header_written = true;
+                }
+                let row: Vec<_> = fields.iter().map(|(_, value)| csv_field(value)).collect();
+                writeln!(self.writer, "{}", row.join(","))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// 把交易中解析出的事件和账户更新按指定格式输出
+pub struct EventPrinter<W> {
+    decoder: EventHandler,
+    // 更新只匹配这些过滤器时，只运行匹配到的过滤器对应的解析器（取并集）；
+    // 匹配到其他过滤器时使用 decoder
+    filter_decoders: Vec<(String, EventHandler)>,
+    // 按过滤器路由时多个 printer 共用一个输出，CSV 表头只写一次
+    output: Arc<Mutex<OutputWriter<W>>>,
+    // 只输出这些事件类型 / 过滤器的更新，为空表示全部输出
+    events: Vec<String>,
+    filters: Vec<String>,
+}
+
+impl EventPrinter<io::Stdout> {
+    pub fn stdout(format: OutputFormat) -> Self {
+        Self::new(OutputWriter::stdout(format))
+    }
+}
+
+impl<W: Write> EventPrinter<W> {
+    pub fn new(output: OutputWriter<W>) -> Self {
+        Self {
+            decoder: EventHandler::new(),
+            filter_decoders: vec![],
+            output: Arc::new(Mutex::new(output)),
+            events: vec![],
+            filters: vec![],
+        }
+    }
+
+    // 写到同一个输出的新 printer，解析器和过滤条件需要另外设置
+    pub fn share_output(&self) -> Self {
+        Self {
+            decoder: EventHandler::new(),
+            filter_decoders: vec![],
+            output: self.output.clone(),
+            events: vec![],
+            filters: vec![],
+        }
+    }
+
+    pub fn with_decoder(mut self, decoder: EventHandler) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    pub fn with_filter_decoder(mut self, filter: impl Into<String>, decoder: EventHandler) -> Self {
+        self.filter_decoders.push((filter.into(), decoder));
+        self
+    }
+
+    pub fn with_events<I, S>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.events = events.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_filters<I, S>(mut self, filters: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.filters = filters.into_iter().map(Into::into).collect();
+        self
+    }
+
+    fn matches_filters(&self, ctx: &UpdateContext) -> bool {
+        self.filters.is_empty() || ctx.filters.iter().any(|f| self.filters.contains(f))
+    }
+
+    fn matches_event(&self, event_type: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event_type)
+    }
+
+    // 同时匹配多个过滤器的交易只解析一次，同一个事件不会重复输出
+    fn decode_events(&self, ctx: &UpdateContext, logs: &[String]) -> Vec<TxEvent> {
+        let decoders: Option<Vec<&EventHandler>> = ctx
+            .filters
+            .iter()
+            .map(|filter| {
+                self.filter_decoders
+                    .iter()
+                    .find(|(name, _)| name == filter)
+                    .map(|(_, decoder)| decoder)
+            })
+            .collect();
+        match decoders {
+            Some(decoders) if !decoders.is_empty() => {
+                let mut events: Vec<TxEvent> = decoders
+                    .into_iter()
+                    .flat_map(|decoder| decoder.decode_logs(logs))
+                    .collect();
+                events.sort_by_key(|event| event.log_index);
+                events.dedup_by(|a, b| a.log_index == b.log_index && a.event_type == b.event_type);
+                events
+            }
+            _ => self.decoder.decode_logs(logs),
+        }
+    }
+
+    // 还有其他 printer 共用输出时返回 None
+    pub fn into_inner(self) -> Option<W> {
+        let output = Arc::into_inner(self.output)?;
+        Some(
+            output
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner)
+                .into_inner(),
+        )
+    }
+
+    fn write_row(&self, fields: &[(&str, String)]) -> io::Result<()> {
+        self.output
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write_row(fields)
+    }
+
+    pub fn print_transaction(
+        &mut self,
+        ctx: &UpdateContext,
+        transaction: &SubscribeUpdateTransaction,
+    ) -> io::Result<()> {
+        let Some(info) = &transaction.transaction else {
+            return Ok(());
+        };
+        if !self.matches_filters(ctx) {
+            return Ok(());
+        }
+        let logs = info
+            .meta
+            .as_ref()
+            .map(|meta| meta.log_messages.as_slice())
+            .unwrap_or_default();
+        let signature = bs58::encode(&info.signature).into_string();
+
+        // 事件带上日志下标、调用深度和 CPI 上层程序，指令的这几列为空
+        let mut events: Vec<(&str, [String; 3], String)> = self
+            .decode_events(ctx, logs)
+            .into_iter()
+            .map(|tx_event| {
+                (
+                    tx_event.event_type,
+                    [
+                        tx_event.log_index.to_string(),
+                        tx_event.depth.to_string(),
+                        tx_event.parent.unwrap_or_default(),
+                    ],
+                    tx_event.event,
+                )
+            })
+            .collect();
+        events.extend(decode_transaction(transaction).into_iter().map(|ix| {
+            (
+                ix.instruction.name(),
+                Default::default(),
+                format!("{:?} {}", ix.instruction, ix.accounts_summary()),
+            )
+        }));
+        // 没有解析出事件和指令的交易（例如钱包、mint 过滤器）也输出一行
+        if events.is_empty() {
+            events.push(("transaction", Default::default(), String::new()));
+        }
+        for (event_type, [log_index, depth, parent], event) in events {
+            if !self.matches_event(event_type) {
+                continue;
+            }
+            self.write_row(&[
+                ("slot", transaction.slot.to_string()),
+                ("signature", signature.clone()),
+                ("filters", ctx.filters.join("|")),
+                ("type", event_type.to_string()),
+                ("log_index", log_index),
+                ("depth", depth),
+                ("parent", parent),
+                ("event", event),
+            ])?;
+        }
+        Ok(())
+    }
+
+    pub fn print_account(
+        &mut self,
+        ctx: &UpdateContext,
+        account: &SubscribeUpdateAccount,
+    ) -> io::Result<()> {
+        let Some(info) = &account.account else {
+            return Ok(());
+        };
+        if !self.matches_filters(ctx) || !self.matches_event("account") {
+            return Ok(());
+        }
+        self.write_row(&[
+            ("slot", account.slot.to_string()),
+            ("pubkey", bs58::encode(&info.pubkey).into_string()),
+            ("filters", ctx.filters.join("|")),
+            ("owner", bs58::encode(&info.owner).into_string()),
+            ("lamports", info.lamports.to_string()),
+            ("data_len", info.data.len().to_string()),
+            ("write_version", info.write_version.to_string()),
+        ])
+    }
+}
+
+impl<W: Write + Send> UpdateDispatcher for EventPrinter<W> {
+    async fn on_account(
+        &mut self,
+        ctx: &UpdateContext,
+        account: SubscribeUpdateAccount,
+    ) -> DispatchResult {
+        Ok(self.print_account(ctx, &account)?)
+    }
+
+    async fn on_transaction(
+        &mut self,
+        ctx: &UpdateContext,
+        transaction: SubscribeUpdateTransaction,
+    ) -> DispatchResult {
+        Ok(self.print_transaction(ctx, &transaction)?)
+    }
+
+    async fn flush(&mut self) -> DispatchResult {
+        Ok(self
+            .output
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_and_json_rows() {
+        let fields = [
+            ("slot", "1".to_string()),
+            ("event", "CreateEvent { name: \"a,b\" }".to_string()),
+        ];
+
+        let mut csv = OutputWriter::new(OutputFormat::Csv, vec![]);
+        csv.write_row(&fields).unwrap();
+        csv.write_row(&fields).unwrap();
+        assert_eq!(
+            String::from_utf8(csv.into_inner()).unwrap(),
+            "slot,event\n1,\"CreateEvent { name: \"\"a,b\"\" }\"\n1,\"CreateEvent { name: \"\"a,b\"\" }\"\n"
+        );
+
+        let mut json = OutputWriter::new(OutputFormat::Json, vec![]);
+        json.write_row(&fields).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json.into_inner()).unwrap();
+        assert_eq!(value["slot"], "1");
+    }
+
+    #[test]
+    fn test_shared_output_writes_header_once() {
+        let account = SubscribeUpdateAccount {
+            account: Some(Default::default()),
+            slot: 7,
+            ..Default::default()
+        };
+        let ctx = |filter: &str| UpdateContext {
+            filters: vec![filter.to_string()],
+            created_at: None,
+        };
+
+        let mut first = EventPrinter::new(OutputWriter::new(OutputFormat::Csv, vec![]));
+        let mut second = first.share_output();
+        second.print_account(&ctx("b"), &account).unwrap();
+        first.print_account(&ctx("a"), &account).unwrap();
+        assert!(first.into_inner().is_none());
+
+        let csv = String::from_utf8(second.into_inner().unwrap()).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.starts_with("slot,pubkey,filters"));
+    }
+
+    #[test]
+    fn test_event_rows_carry_log_index_and_cpi_parent() {
+        use crate::{
+            mock,
+            model::{EventTrait, pumpamm::BuyEvent},
+        };
+        use yellowstone_grpc_proto::geyser::subscribe_update::UpdateOneof;
+
+        let jupiter = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+        let logs = vec![
+            format!("Program {} invoke [1]", jupiter),
+            "Program pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA invoke [2]".to_string(),
+            mock::program_data_log(
+                BuyEvent::discriminator(),
+                &borsh::to_vec(&BuyEvent::default()).unwrap(),
+            ),
+        ];
+        let Some(UpdateOneof::Transaction(transaction)) =
+            mock::transaction_update(5, &[1u8; 64], logs).update_oneof
+        else {
+            panic!("expected a transaction update");
+        };
+
+        let mut printer = EventPrinter::new(OutputWriter::new(OutputFormat::Json, vec![]))
+            .with_events([BuyEvent::NAME]);
+        printer
+            .print_transaction(&UpdateContext::default(), &transaction)
+            .unwrap();
+        let row: serde_json::Value =
+            serde_json::from_slice(&printer.into_inner().unwrap()).unwrap();
+        assert_eq!(row["type"], BuyEvent::NAME);
+        assert_eq!(row["log_index"], "2");
+        assert_eq!(row["depth"], "2");
+        assert_eq!(row["parent"], jupiter);
+    }
+}
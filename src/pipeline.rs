@@ -0,0 +1,530 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use log::warn;
+use tokio::sync::Notify;
+use yellowstone_grpc_proto::{geyser::SubscribeUpdate, prost::Message};
+
+use crate::error::ClientError;
+
+const DEFAULT_CAPACITY: usize = 10_000;
+// 每丢弃这么多条打印一次警告
+const DROP_LOG_EVERY: u64 = 1000;
+
+// 队列满时的处理方式
+#[derive(Clone, Debug, Default)]
+pub enum OverflowPolicy {
+    // 等待处理端腾出空间（会反压 gRPC 流）
+    #[default]
+    Block,
+    DropOldest,
+    DropNewest,
+    // 溢出的数据按顺序写入磁盘文件，处理端追上后再读回
+    SpillToDisk(PathBuf),
+}
+
+#[derive(Clone, Debug)]
+pub struct PipelineConfig {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct QueueMetrics {
+    // 当前排队数量（含磁盘上的）
+    pub depth: AtomicU64,
+    pub max_depth: AtomicU64,
+    pub enqueued: AtomicU64,
+    pub processed: AtomicU64,
+    pub dropped: AtomicU64,
+    pub spilled: AtomicU64,
+}
+
+impl QueueMetrics {
+    fn on_enqueue(&self) {
+        self.enqueued.fetch_add(1, Ordering::Relaxed);
+        let depth = self.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        self.max_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    fn on_dequeue(&self) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn on_drop(&self, reason: &str) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        if dropped % DROP_LOG_EVERY == 1 {
+            warn!(
+                "Pipeline queue full, {} ({} dropped so far)",
+                reason, dropped
+            );
+        }
+    }
+}
+
+// 按 [长度 u32 LE][protobuf] 的格式顺序写入，读完后清空文件
+struct SpillFile {
+    writer: File,
+    reader: File,
+    // 已写入的完整帧的末尾位置
+    end: u64,
+    // 文件中还没读回的帧数
+    frames: usize,
+}
+
+impl SpillFile {
+    fn open(path: &Path) -> Result<Self, ClientError> {
+        let writer = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        let reader = File::open(path)?;
+        Ok(Self {
+            writer,
+            reader,
+            end: 0,
+            frames: 0,
+        })
+    }
+
+    // 长度和内容一次写入；失败时截掉写了一半的帧，文件中只保留完整的帧
+    fn push(&mut self, update: &SubscribeUpdate) -> io::Result<()> {
+        let len = update.encoded_len();
+        let mut frame = Vec::with_capacity(4 + len);
+        frame.extend_from_slice(&(len as u32).to_le_bytes());
+        update.encode(&mut frame).map_err(io::Error::other)?;
+
+        if let Err(e) = self.writer.write_all(&frame) {
+            self.writer.set_len(self.end)?;
+            self.writer.seek(SeekFrom::Start(self.end))?;
+            return Err(e);
+        }
+        self.end += frame.len() as u64;
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> io::Result<Option<SubscribeUpdate>> {
+        if self.frames == 0 {
+            return Ok(None);
+        }
+        let mut len = [0u8; 4];
+        self.reader.read_exact(&mut len)?;
+        let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut bytes)?;
+        let update = SubscribeUpdate::decode(bytes.as_slice()).map_err(io::Error::other)?;
+        self.frames -= 1;
+
+        if self.frames == 0
+            && let Err(e) = self.truncate()
+        {
+            warn!("Failed to truncate spill file: {:?}", e);
+        }
+        Ok(Some(update))
+    }
+
+    // 读失败后文件内容已不可信，丢弃剩下的帧，返回丢弃的数量
+    fn discard(&mut self) -> usize {
+        let lost = std::mem::take(&mut self.frames);
+        if let Err(e) = self.truncate() {
+            warn!("Failed to truncate spill file: {:?}", e);
+        }
+        lost
+    }
+
+    fn truncate(&mut self) -> io::Result<()> {
+        self.end = 0;
+        self.writer.set_len(0)?;
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.reader.seek(SeekFrom::Start(0))?;
+        Ok(())
+    }
+}
+
+struct State {
+    buffer: VecDeque<SubscribeUpdate>,
+    // 决定写入磁盘、还没回到内存的数量（含正在写入的）
+    spilling: usize,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    // 文件读写在阻塞线程中进行，不持有 state 的锁
+    spill: Option<Arc<Mutex<SpillFile>>>,
+    capacity: usize,
+    overflow: OverflowPolicy,
+    not_empty: Notify,
+    not_full: Notify,
+    senders: AtomicUsize,
+    receiver_closed: AtomicBool,
+    metrics: Arc<QueueMetrics>,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn spill_file(&self) -> Arc<Mutex<SpillFile>> {
+        self.spill.clone().expect("spill file")
+    }
+}
+
+pub struct UpdateSender {
+    shared: Arc<Shared>,
+}
+
+pub struct UpdateReceiver {
+    shared: Arc<Shared>,
+}
+
+// 创建有界队列，读流和处理分别在两端运行
+pub fn bounded(
+    config: PipelineConfig,
+    metrics: Arc<QueueMetrics>,
+) -> Result<(UpdateSender, UpdateReceiver), ClientError> {
+    let spill = match &config.overflow {
+        OverflowPolicy::SpillToDisk(path) => Some(Arc::new(Mutex::new(SpillFile::open(path)?))),
+        _ => None,
+    };
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(config.capacity.min(DEFAULT_CAPACITY)),
+            spilling: 0,
+        }),
+        spill,
+        capacity: config.capacity.max(1),
+        overflow: config.overflow,
+        not_empty: Notify::new(),
+        not_full: Notify::new(),
+        senders: AtomicUsize::new(1),
+        receiver_closed: AtomicBool::new(false),
+        metrics,
+    });
+    Ok((
+        UpdateSender {
+            shared: shared.clone(),
+        },
+        UpdateReceiver { shared },
+    ))
+}
+
+impl UpdateSender {
+    pub fn metrics(&self) -> Arc<QueueMetrics> {
+        self.shared.metrics.clone()
+    }
+
+    // 处理端已关闭时返回错误
+    pub async fn send(&self, update: SubscribeUpdate) -> Result<(), ClientError> {
+        let shared = &self.shared;
+        loop {
+            if shared.receiver_closed.load(Ordering::Acquire) {
+                return Err(ClientError::Handler("pipeline receiver closed".to_string()));
+            }
+
+            let not_full = shared.not_full.notified();
+            {
+                let mut state = shared.state();
+                if state.buffer.len() < shared.capacity && state.spilling == 0 {
+                    state.buffer.push_back(update);
+                    shared.metrics.on_enqueue();
+                    drop(state);
+                    shared.not_empty.notify_one();
+                    return Ok(());
+                }
+
+                match &shared.overflow {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::DropNewest => {
+                        shared.metrics.on_drop("dropping newest update");
+                        return Ok(());
+                    }
+                    OverflowPolicy::DropOldest => {
+                        state.buffer.pop_front();
+                        state.buffer.push_back(update);
+                        shared.metrics.on_drop("dropping oldest update");
+                        return Ok(());
+                    }
+                    OverflowPolicy::SpillToDisk(_) => {
+                        // 一旦开始写磁盘，后续数据也必须写磁盘以保证顺序
+                        state.spilling += 1;
+                        shared.metrics.on_enqueue();
+                    }
+                }
+            }
+            if matches!(shared.overflow, OverflowPolicy::SpillToDisk(_)) {
+                return self.spill(update).await;
+            }
+            not_full.await;
+        }
+    }
+
+    async fn spill(&self, update: SubscribeUpdate) -> Result<(), ClientError> {
+        let shared = &self.shared;
+        let spill = shared.spill_file();
+        let result = tokio::task::spawn_blocking(move || {
+            spill
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(&update)
+        })
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)));
+
+        match result {
+            Ok(()) => {
+                shared.metrics.spilled.fetch_add(1, Ordering::Relaxed);
+                shared.not_empty.notify_one();
+                Ok(())
+            }
+            Err(e) => {
+                shared.state().spilling -= 1;
+                shared.metrics.depth.fetch_sub(1, Ordering::Relaxed);
+                shared.metrics.on_drop("failed to spill update");
+                // 处理端可能在等这一条
+                shared.not_empty.notify_one();
+                Err(e.into())
+            }
+        }
+    }
+}
+
+impl Clone for UpdateSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for UpdateSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.not_empty.notify_one();
+        }
+    }
+}
+
+impl UpdateReceiver {
+    // 所有发送端关闭且队列为空时返回 None
+    pub async fn recv(&self) -> Option<SubscribeUpdate> {
+        let shared = &self.shared;
+        loop {
+            let not_empty = shared.not_empty.notified();
+            let (update, refill) = {
+                let mut state = shared.state();
+                let update = state.buffer.pop_front();
+                // 内存中有空位时，从磁盘读回一条
+                let refill = state.spilling > 0 && state.buffer.len() < shared.capacity;
+                if update.is_none() && !refill && shared.senders.load(Ordering::Acquire) == 0 {
+                    return None;
+                }
+                (update, refill)
+            };
+
+            let refilled = refill && self.refill().await;
+            if let Some(update) = update {
+                shared.metrics.on_dequeue();
+                shared.not_full.notify_one();
+                return Some(update);
+            }
+            // 还在写入磁盘的数据写完后会通知
+            if !refilled {
+                not_empty.await;
+            }
+        }
+    }
+
+    // 返回是否读回了数据；读失败时文件中剩下的数据全部丢弃
+    async fn refill(&self) -> bool {
+        let shared = &self.shared;
+        let spill = shared.spill_file();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut spill = spill.lock().unwrap_or_else(PoisonError::into_inner);
+            spill.pop().map_err(|e| (e, spill.discard()))
+        })
+        .await
+        .unwrap_or_else(|e| Err((io::Error::other(e), 0)));
+
+        let mut state = shared.state();
+        match result {
+            Ok(Some(update)) => {
+                state.spilling -= 1;
+                state.buffer.push_back(update);
+                true
+            }
+            // 对应的数据还在写入
+            Ok(None) => false,
+            Err((e, lost)) => {
+                warn!("Failed to read spilled update, {} lost: {:?}", lost, e);
+                state.spilling -= lost;
+                shared
+                    .metrics
+                    .depth
+                    .fetch_sub(lost as u64, Ordering::Relaxed);
+                shared
+                    .metrics
+                    .dropped
+                    .fetch_add(lost as u64, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        let state = self.shared.state();
+        state.buffer.len() + state.spilling
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for UpdateReceiver {
+    fn drop(&mut self) {
+        self.shared.receiver_closed.store(true, Ordering::Release);
+        self.shared.not_full.notify_one();
+        if let OverflowPolicy::SpillToDisk(path) = &self.shared.overflow {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yellowstone_grpc_proto::geyser::{SubscribeUpdateSlot, subscribe_update::UpdateOneof};
+
+    fn slot_update(slot: u64) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn slot_of(update: SubscribeUpdate) -> u64 {
+        match update.update_oneof {
+            Some(UpdateOneof::Slot(slot)) => slot.slot,
+            _ => panic!("unexpected update"),
+        }
+    }
+
+    async fn drain(rx: &UpdateReceiver) -> Vec<u64> {
+        let mut slots = vec![];
+        while !rx.is_empty() {
+            slots.push(slot_of(rx.recv().await.unwrap()));
+        }
+        slots
+    }
+
+    fn config(overflow: OverflowPolicy) -> PipelineConfig {
+        PipelineConfig {
+            capacity: 2,
+            overflow,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_policies() {
+        let metrics = Arc::new(QueueMetrics::default());
+        let (tx, rx) = bounded(config(OverflowPolicy::DropOldest), metrics.clone()).unwrap();
+        for slot in 1..=4 {
+            tx.send(slot_update(slot)).await.unwrap();
+        }
+        assert_eq!(drain(&rx).await, vec![3, 4]);
+        assert_eq!(metrics.dropped.load(Ordering::Relaxed), 2);
+
+        let (tx, rx) = bounded(config(OverflowPolicy::DropNewest), Default::default()).unwrap();
+        for slot in 1..=4 {
+            tx.send(slot_update(slot)).await.unwrap();
+        }
+        assert_eq!(drain(&rx).await, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_spill_to_disk_keeps_order() {
+        let path = std::env::temp_dir().join(format!("grpc_jh_spill_{}", std::process::id()));
+        let metrics = Arc::new(QueueMetrics::default());
+        let (tx, rx) = bounded(config(OverflowPolicy::SpillToDisk(path)), metrics.clone()).unwrap();
+        for slot in 1..=5 {
+            tx.send(slot_update(slot)).await.unwrap();
+        }
+        assert_eq!(metrics.spilled.load(Ordering::Relaxed), 3);
+        assert_eq!(metrics.max_depth.load(Ordering::Relaxed), 5);
+        assert_eq!(drain(&rx).await, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_block_waits_for_receiver() {
+        let (tx, rx) = bounded(config(OverflowPolicy::Block), Default::default()).unwrap();
+        let producer = tokio::spawn(async move {
+            for slot in 1..=5 {
+                tx.send(slot_update(slot)).await.unwrap();
+            }
+        });
+
+        let mut slots = vec![];
+        while let Some(update) = rx.recv().await {
+            slots.push(slot_of(update));
+        }
+        producer.await.unwrap();
+        assert_eq!(slots, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_spill_file_is_discarded() {
+        let path =
+            std::env::temp_dir().join(format!("grpc_jh_spill_corrupt_{}", std::process::id()));
+        let metrics = Arc::new(QueueMetrics::default());
+        let (tx, rx) = bounded(
+            config(OverflowPolicy::SpillToDisk(path.clone())),
+            metrics.clone(),
+        )
+        .unwrap();
+        for slot in 1..=5 {
+            tx.send(slot_update(slot)).await.unwrap();
+        }
+        // 截断后第一帧不完整，磁盘上的 3 条都读不回来
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(2)
+            .unwrap();
+
+        assert_eq!(drain(&rx).await, vec![1, 2]);
+        assert_eq!(metrics.dropped.load(Ordering::Relaxed), 3);
+        assert_eq!(metrics.depth.load(Ordering::Relaxed), 0);
+
+        // 文件已重置，之后溢出的数据正常写入读回
+        for slot in 6..=9 {
+            tx.send(slot_update(slot)).await.unwrap();
+        }
+        assert_eq!(drain(&rx).await, vec![6, 7, 8, 9]);
+    }
+}
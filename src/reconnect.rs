@@ -0,0 +1,370 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use yellowstone_grpc_proto::geyser::{
+    SubscribeRequest, SubscribeRequestPing, subscribe_update::UpdateOneof,
+};
+
+use crate::slots::{GapKind, SlotGap};
+
+// 未收到 pong 的 ping 最多保留的数量
+const MAX_PENDING_PINGS: usize = 64;
+// 去重至少保留的 slot 数量，processed 级别下相邻 slot 的交易可能交错到达
+const MIN_REPLAY_SLOTS: u64 = 32;
+
+// 重连策略：指数退避 + 抖动
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    // 抖动比例，0.0 ~ 1.0，实际等待时间在 [delay * (1 - jitter), delay] 之间
+    pub jitter: f64,
+    // None 表示无限重连
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    // attempt 从 1 开始计数
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let delay = self.initial_backoff.mul_f64(exp).min(self.max_backoff);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        let factor = rand::thread_rng().gen_range((1.0 - jitter)..=1.0);
+        delay.mul_f64(factor)
+    }
+
+    pub fn should_retry(&self, attempt: u32) -> bool {
+        self.max_attempts.is_none_or(|max| attempt <= max)
+    }
+}
+
+// 存活检测：定时主动 ping，超过 stale_timeout 没有收到任何消息就断开重连
+#[derive(Clone, Debug)]
+pub struct WatchdogConfig {
+    pub ping_interval: Duration,
+    pub stale_timeout: Duration,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(10),
+            stale_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+// 记录已发送的 ping，收到对应 id 的 pong 时计算往返时间
+#[derive(Debug, Default)]
+pub struct PingTracker {
+    next_id: i32,
+    pending: VecDeque<(i32, Instant)>,
+}
+
+impl PingTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_ping(&mut self, now: Instant) -> SubscribeRequest {
+        self.next_id = self.next_id.wrapping_add(1);
+        if self.pending.len() >= MAX_PENDING_PINGS {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((self.next_id, now));
+        SubscribeRequest {
+            ping: Some(SubscribeRequestPing { id: self.next_id }),
+            ..Default::default()
+        }
+    }
+
+    pub fn on_pong(&mut self, id: i32, now: Instant) -> Option<Duration> {
+        let index = self
+            .pending
+            .iter()
+            .position(|(ping_id, _)| *ping_id == id)?;
+        let (_, sent_at) = self.pending.remove(index)?;
+        // 比这个 ping 更早发出的已经不会再有 pong 了
+        self.pending.drain(..index);
+        Some(now.saturating_duration_since(sent_at))
+    }
+}
+
+// 重连（from_slot = 最后处理的 slot）和回填时服务端会重新推送已处理过的更新，
+// 按签名（账户为 pubkey + write_version）去重，只保留最近 window 个 slot 的记录
+#[derive(Debug)]
+pub struct ReplayDedup {
+    window: u64,
+    // 已交付的交易 / 账户更新中最大的 slot，用于断线后的 from_slot 续订
+    last_slot: Option<u64>,
+    delivered: BTreeMap<u64, HashSet<Vec<u8>>>,
+}
+
+impl ReplayDedup {
+    pub fn new(window: u64) -> Self {
+        Self {
+            window: window.max(MIN_REPLAY_SLOTS),
+            last_slot: None,
+            delivered: BTreeMap::new(),
+        }
+    }
+
+    pub fn last_slot(&self) -> Option<u64> {
+        self.last_slot
+    }
+
+    // 回放不可用、改为从最新位置订阅时调用，已记录的签名继续用于去重
+    pub fn reset_slot(&mut self) {
+        self.last_slot = None;
+    }
+
+    // 返回 false 表示这条更新已经交付过；只有交易和账户更新参与去重
+    pub fn observe(&mut self, update: &UpdateOneof) -> bool {
+        let (slot, key) = match update {
+            UpdateOneof::Transaction(tx) => {
+                let Some(info) = &tx.transaction else {
+                    return true;
+                };
+                (tx.slot, info.signature.clone())
+            }
+            UpdateOneof::Account(account) => {
+                let Some(info) = &account.account else {
+                    return true;
+                };
+                let mut key = info.pubkey.clone();
+                key.extend_from_slice(&info.write_version.to_le_bytes());
+                (account.slot, key)
+            }
+            _ => return true,
+        };
+        if !self.delivered.entry(slot).or_default().insert(key) {
+            return false;
+        }
+
+        let last_slot = self.last_slot.map_or(slot, |last| last.max(slot));
+        self.last_slot = Some(last_slot);
+        let floor = last_slot.saturating_sub(self.window);
+        self.delivered = self.delivered.split_off(&floor);
+        true
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SubscriptionEvent {
+    Connected {
+        endpoint: String,
+        attempt: u32,
+        from_slot: Option<u64>,
+    },
+    Disconnected {
+        reason: String,
+        last_slot: Option<u64>,
+    },
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+    },
+    // 超过 stale_timeout 没有收到任何消息
+    Stalled {
+        idle: Duration,
+    },
+    // 断线期间的 slot 正在通过 from_slot 回放
+    Recovered {
+        from_slot: u64,
+        to_slot: Option<u64>,
+    },
+    // 服务端无法回放 from_slot，该区间的数据已丢失
+    Gap {
+        from_slot: u64,
+        to_slot: Option<u64>,
+    },
+    // slot 订阅中发现的不连续区间
+    SlotGap(SlotGap),
+    // 断开当前连接，用 from_slot 回填丢失的区间
+    Backfilling {
+        from_slot: u64,
+        to_slot: u64,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct SubscriptionMetrics {
+    pub reconnects: AtomicU64,
+    pub gaps: AtomicU64,
+    pub recovered_slots: AtomicU64,
+    pub lost_slots: AtomicU64,
+    pub last_slot: AtomicU64,
+    pub stalls: AtomicU64,
+    pub skipped_slots: AtomicU64,
+    pub dropped_slots: AtomicU64,
+    pub backfills: AtomicU64,
+    pub pings_sent: AtomicU64,
+    pub pongs_received: AtomicU64,
+    // 最近一次 ping 往返时间（微秒）
+    pub last_rtt_us: AtomicU64,
+}
+
+impl SubscriptionMetrics {
+    pub fn record(&self, event: &SubscriptionEvent) {
+        match event {
+            SubscriptionEvent::Reconnecting { .. } => {
+                self.reconnects.fetch_add(1, Ordering::Relaxed);
+            }
+            SubscriptionEvent::Stalled { .. } => {
+                self.stalls.fetch_add(1, Ordering::Relaxed);
+            }
+            SubscriptionEvent::Recovered {
+                from_slot,
+                to_slot: Some(to_slot),
+            } => {
+                self.recovered_slots
+                    .fetch_add(to_slot.saturating_sub(*from_slot), Ordering::Relaxed);
+            }
+            SubscriptionEvent::Gap { from_slot, to_slot } => {
+                self.gaps.fetch_add(1, Ordering::Relaxed);
+                if let Some(to_slot) = to_slot {
+                    self.lost_slots
+                        .fetch_add(to_slot.saturating_sub(*from_slot), Ordering::Relaxed);
+                }
+            }
+            SubscriptionEvent::SlotGap(gap) => match gap.kind {
+                GapKind::Skipped | GapKind::Dead => {
+                    self.skipped_slots
+                        .fetch_add(gap.slot_count(), Ordering::Relaxed);
+                }
+                GapKind::Dropped => {
+                    self.dropped_slots
+                        .fetch_add(gap.slot_count(), Ordering::Relaxed);
+                }
+                GapKind::Unknown => {}
+            },
+            SubscriptionEvent::Backfilling { .. } => {
+                self.backfills.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn last_slot(&self) -> Option<u64> {
+        match self.last_slot.load(Ordering::Relaxed) {
+            0 => None,
+            slot => Some(slot),
+        }
+    }
+
+    pub fn set_last_slot(&self, slot: u64) {
+        self.last_slot.fetch_max(slot, Ordering::Relaxed);
+    }
+
+    pub fn record_rtt(&self, rtt: Duration) {
+        self.pongs_received.fetch_add(1, Ordering::Relaxed);
+        self.last_rtt_us
+            .store(rtt.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn last_rtt(&self) -> Option<Duration> {
+        match self.pongs_received.load(Ordering::Relaxed) {
+            0 => None,
+            _ => Some(Duration::from_micros(
+                self.last_rtt_us.load(Ordering::Relaxed),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yellowstone_grpc_proto::geyser::{
+        SubscribeUpdateSlot, SubscribeUpdateTransaction, SubscribeUpdateTransactionInfo,
+    };
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = ReconnectPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(20), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_ping_rtt() {
+        let mut tracker = PingTracker::new();
+        let start = Instant::now();
+        let first = tracker.next_ping(start).ping.unwrap().id;
+        let second = tracker.next_ping(start).ping.unwrap().id;
+        assert_eq!(second, first + 1);
+
+        let rtt = tracker.on_pong(second, start + Duration::from_millis(20));
+        assert_eq!(rtt, Some(Duration::from_millis(20)));
+        // 更早的 ping 已经被丢弃
+        assert_eq!(tracker.on_pong(first, start), None);
+    }
+
+    #[test]
+    fn test_backoff_jitter_range() {
+        let policy = ReconnectPolicy::default();
+        for attempt in 1..10 {
+            let delay = policy.backoff(attempt);
+            assert!(delay <= policy.max_backoff);
+            assert!(delay >= policy.initial_backoff / 2);
+        }
+    }
+
+    fn transaction(slot: u64, signature: u8) -> UpdateOneof {
+        UpdateOneof::Transaction(SubscribeUpdateTransaction {
+            slot,
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: vec![signature; 64],
+                ..Default::default()
+            }),
+        })
+    }
+
+    #[test]
+    fn test_replay_dedup() {
+        let mut dedup = ReplayDedup::new(0);
+        assert!(dedup.observe(&transaction(10, 1)));
+        assert!(dedup.observe(&transaction(11, 2)));
+        assert!(dedup.observe(&UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot: 12,
+            ..Default::default()
+        })));
+        // slot 更新不推进 last_slot
+        assert_eq!(dedup.last_slot(), Some(11));
+
+        // 从 slot 10 回填时，已经交付过的交易被跳过，缺失的交易正常交付
+        assert!(!dedup.observe(&transaction(10, 1)));
+        assert!(dedup.observe(&transaction(10, 3)));
+        assert!(!dedup.observe(&transaction(11, 2)));
+        assert_eq!(dedup.last_slot(), Some(11));
+
+        // 超出窗口的 slot 不再记录
+        assert!(dedup.observe(&transaction(11 + MIN_REPLAY_SLOTS + 1, 4)));
+        assert!(dedup.observe(&transaction(10, 1)));
+    }
+}
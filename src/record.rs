@@ -0,0 +1,104 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use yellowstone_grpc_proto::{geyser::SubscribeUpdate, prost::Message};
+
+use crate::engine::{DispatchResult, UpdateDispatcher};
+
+// 按 [长度 u32 LE][protobuf] 的格式保存原始更新，之后可以离线回放
+pub struct UpdateRecorder {
+    writer: BufWriter<File>,
+    recorded: u64,
+}
+
+impl UpdateRecorder {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            recorded: 0,
+        })
+    }
+
+    pub fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    pub fn write(&mut self, update: &SubscribeUpdate) -> io::Result<()> {
+        let bytes = update.encode_to_vec();
+        self.writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&bytes)?;
+        self.recorded += 1;
+        Ok(())
+    }
+}
+
+impl UpdateDispatcher for UpdateRecorder {
+    async fn on_update(&mut self, update: SubscribeUpdate) -> DispatchResult {
+        Ok(self.write(&update)?)
+    }
+
+    async fn flush(&mut self) -> DispatchResult {
+        Ok(self.writer.flush()?)
+    }
+}
+
+// 按写入顺序读取录制的更新
+pub struct UpdateReader<R> {
+    reader: R,
+}
+
+impl UpdateReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: Read> UpdateReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+}
+
+impl<R: Read> Iterator for UpdateReader<R> {
+    type Item = io::Result<SubscribeUpdate>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut len = [0u8; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return None,
+            Err(e) => return Some(Err(e)),
+        }
+        let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+        if let Err(e) = self.reader.read_exact(&mut bytes) {
+            return Some(Err(e));
+        }
+        Some(SubscribeUpdate::decode(bytes.as_slice()).map_err(io::Error::other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    #[tokio::test]
+    async fn test_record_and_read_back() {
+        let path = std::env::temp_dir().join(format!("grpc_jh_record_{}", std::process::id()));
+        let mut recorder = UpdateRecorder::create(&path).unwrap();
+        recorder.on_update(mock::slot_update(1)).await.unwrap();
+        recorder.on_update(mock::slot_update(2)).await.unwrap();
+        recorder.flush().await.unwrap();
+        assert_eq!(recorder.recorded(), 2);
+
+        let updates: Vec<_> = UpdateReader::open(&path)
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(updates, vec![mock::slot_update(1), mock::slot_update(2)]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
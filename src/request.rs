@@ -0,0 +1,646 @@
+use std::{fmt, str::FromStr};
+
+use base64::{Engine, engine::general_purpose};
+use solana_sdk::{bs58, pubkey::Pubkey, signature::Signature};
+use yellowstone_grpc_proto::geyser::{
+    CommitmentLevel, SubscribeRequest, SubscribeRequestAccountsDataSlice,
+    SubscribeRequestFilterAccounts, SubscribeRequestFilterAccountsFilter,
+    SubscribeRequestFilterAccountsFilterLamports, SubscribeRequestFilterAccountsFilterMemcmp,
+    SubscribeRequestFilterBlocks, SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterEntry,
+    SubscribeRequestFilterSlots, SubscribeRequestFilterTransactions,
+    subscribe_request_filter_accounts_filter::Filter as AccountsFilterOneof,
+    subscribe_request_filter_accounts_filter_lamports::Cmp as LamportsCmpOneof,
+    subscribe_request_filter_accounts_filter_memcmp::Data as MemcmpData,
+};
+
+// memcmp 数据的最大长度，与 Solana RPC 保持一致
+const MAX_MEMCMP_BYTES: usize = 128;
+
+// 服务端对过滤器数量的限制，不同服务商配置不同
+#[derive(Clone, Debug)]
+pub struct FilterLimits {
+    // 每种类型（accounts/transactions/...）最多的命名过滤器数量
+    pub max_filters: usize,
+    // 单个过滤器中 account/owner/account_include 等列表的最大长度
+    pub max_accounts: usize,
+    // 单个 accounts 过滤器中 memcmp/datasize/lamports 条件的数量
+    pub max_account_conditions: usize,
+    pub max_data_slices: usize,
+}
+
+impl Default for FilterLimits {
+    fn default() -> Self {
+        Self {
+            max_filters: 100,
+            max_accounts: 10_000,
+            max_account_conditions: 4,
+            max_data_slices: 16,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RequestError {
+    InvalidPubkey {
+        filter: String,
+        value: String,
+    },
+    InvalidSignature {
+        filter: String,
+        value: String,
+    },
+    InvalidMemcmp {
+        filter: String,
+        reason: String,
+    },
+    TooManyFilters {
+        kind: &'static str,
+        count: usize,
+        max: usize,
+    },
+    TooManyValues {
+        filter: String,
+        field: &'static str,
+        count: usize,
+        max: usize,
+    },
+    InvalidDataSlice(String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::InvalidPubkey { filter, value } => {
+                write!(f, "filter `{}`: invalid pubkey `{}`", filter, value)
+            }
+            RequestError::InvalidSignature { filter, value } => {
+                write!(f, "filter `{}`: invalid signature `{}`", filter, value)
+            }
+            RequestError::InvalidMemcmp { filter, reason } => {
+                write!(f, "filter `{}`: invalid memcmp: {}", filter, reason)
+            }
+            RequestError::TooManyFilters { kind, count, max } => {
+                write!(f, "{} {} filters, max {}", count, kind, max)
+            }
+            RequestError::TooManyValues {
+                filter,
+                field,
+                count,
+                max,
+            } => write!(
+                f,
+                "filter `{}`: {} values in `{}`, max {}",
+                filter, count, field, max
+            ),
+            RequestError::InvalidDataSlice(reason) => write!(f, "invalid data slice: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LamportsCmp {
+    Eq(u64),
+    Ne(u64),
+    Lt(u64),
+    Gt(u64),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AccountsFilter {
+    inner: SubscribeRequestFilterAccounts,
+}
+
+impl AccountsFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn account(mut self, pubkey: impl Into<String>) -> Self {
+        self.inner.account.push(pubkey.into());
+        self
+    }
+
+    pub fn accounts<I, S>(mut self, pubkeys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inner
+            .account
+            .extend(pubkeys.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn owner(mut self, pubkey: impl Into<String>) -> Self {
+        self.inner.owner.push(pubkey.into());
+        self
+    }
+
+    pub fn memcmp(self, offset: u64, bytes: Vec<u8>) -> Self {
+        self.memcmp_data(offset, MemcmpData::Bytes(bytes))
+    }
+
+    pub fn memcmp_base58(self, offset: u64, data: impl Into<String>) -> Self {
+        self.memcmp_data(offset, MemcmpData::Base58(data.into()))
+    }
+
+    pub fn memcmp_base64(self, offset: u64, data: impl Into<String>) -> Self {
+        self.memcmp_data(offset, MemcmpData::Base64(data.into()))
+    }
+
+    fn memcmp_data(self, offset: u64, data: MemcmpData) -> Self {
+        self.condition(AccountsFilterOneof::Memcmp(
+            SubscribeRequestFilterAccountsFilterMemcmp {
+                offset,
+                data: Some(data),
+            },
+        ))
+    }
+
+    pub fn datasize(self, size: u64) -> Self {
+        self.condition(AccountsFilterOneof::Datasize(size))
+    }
+
+    pub fn token_account_state(self) -> Self {
+        self.condition(AccountsFilterOneof::TokenAccountState(true))
+    }
+
+    pub fn lamports(self, cmp: LamportsCmp) -> Self {
+        let cmp = match cmp {
+            LamportsCmp::Eq(v) => LamportsCmpOneof::Eq(v),
+            LamportsCmp::Ne(v) => LamportsCmpOneof::Ne(v),
+            LamportsCmp::Lt(v) => LamportsCmpOneof::Lt(v),
+            LamportsCmp::Gt(v) => LamportsCmpOneof::Gt(v),
+        };
+        self.condition(AccountsFilterOneof::Lamports(
+            SubscribeRequestFilterAccountsFilterLamports { cmp: Some(cmp) },
+        ))
+    }
+
+    pub fn nonempty_txn_signature(mut self, nonempty: bool) -> Self {
+        self.inner.nonempty_txn_signature = Some(nonempty);
+        self
+    }
+
+    fn condition(mut self, filter: AccountsFilterOneof) -> Self {
+        self.inner
+            .filters
+            .push(SubscribeRequestFilterAccountsFilter {
+                filter: Some(filter),
+            });
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TransactionsFilter {
+    inner: SubscribeRequestFilterTransactions,
+}
+
+impl TransactionsFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vote(mut self, vote: bool) -> Self {
+        self.inner.vote = Some(vote);
+        self
+    }
+
+    pub fn failed(mut self, failed: bool) -> Self {
+        self.inner.failed = Some(failed);
+        self
+    }
+
+    pub fn signature(mut self, signature: impl Into<String>) -> Self {
+        self.inner.signature = Some(signature.into());
+        self
+    }
+
+    pub fn include(mut self, pubkey: impl Into<String>) -> Self {
+        self.inner.account_include.push(pubkey.into());
+        self
+    }
+
+    pub fn include_all<I, S>(mut self, pubkeys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inner
+            .account_include
+            .extend(pubkeys.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn exclude(mut self, pubkey: impl Into<String>) -> Self {
+        self.inner.account_exclude.push(pubkey.into());
+        self
+    }
+
+    pub fn required(mut self, pubkey: impl Into<String>) -> Self {
+        self.inner.account_required.push(pubkey.into());
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BlocksFilter {
+    inner: SubscribeRequestFilterBlocks,
+}
+
+impl BlocksFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include(mut self, pubkey: impl Into<String>) -> Self {
+        self.inner.account_include.push(pubkey.into());
+        self
+    }
+
+    pub fn include_transactions(mut self, include: bool) -> Self {
+        self.inner.include_transactions = Some(include);
+        self
+    }
+
+    pub fn include_accounts(mut self, include: bool) -> Self {
+        self.inner.include_accounts = Some(include);
+        self
+    }
+
+    pub fn include_entries(mut self, include: bool) -> Self {
+        self.inner.include_entries = Some(include);
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SubscribeRequestBuilder {
+    request: SubscribeRequest,
+    limits: FilterLimits,
+}
+
+impl SubscribeRequestBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limits(mut self, limits: FilterLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn accounts(mut self, name: impl Into<String>, filter: AccountsFilter) -> Self {
+        self.request.accounts.insert(name.into(), filter.inner);
+        self
+    }
+
+    pub fn slots(
+        mut self,
+        name: impl Into<String>,
+        filter_by_commitment: bool,
+        interslot_updates: bool,
+    ) -> Self {
+        self.request.slots.insert(
+            name.into(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(filter_by_commitment),
+                interslot_updates: Some(interslot_updates),
+            },
+        );
+        self
+    }
+
+    pub fn transactions(mut self, name: impl Into<String>, filter: TransactionsFilter) -> Self {
+        self.request.transactions.insert(name.into(), filter.inner);
+        self
+    }
+
+    pub fn transactions_status(
+        mut self,
+        name: impl Into<String>,
+        filter: TransactionsFilter,
+    ) -> Self {
+        self.request
+            .transactions_status
+            .insert(name.into(), filter.inner);
+        self
+    }
+
+    pub fn blocks(mut self, name: impl Into<String>, filter: BlocksFilter) -> Self {
+        self.request.blocks.insert(name.into(), filter.inner);
+        self
+    }
+
+    pub fn blocks_meta(mut self, name: impl Into<String>) -> Self {
+        self.request
+            .blocks_meta
+            .insert(name.into(), SubscribeRequestFilterBlocksMeta {});
+        self
+    }
+
+    pub fn entry(mut self, name: impl Into<String>) -> Self {
+        self.request
+            .entry
+            .insert(name.into(), SubscribeRequestFilterEntry {});
+        self
+    }
+
+    pub fn data_slice(mut self, offset: u64, length: u64) -> Self {
+        self.request
+            .accounts_data_slice
+            .push(SubscribeRequestAccountsDataSlice { offset, length });
+        self
+    }
+
+    pub fn commitment(mut self, commitment: CommitmentLevel) -> Self {
+        self.request.commitment = Some(commitment.into());
+        self
+    }
+
+    pub fn from_slot(mut self, slot: u64) -> Self {
+        self.request.from_slot = Some(slot);
+        self
+    }
+
+    pub fn build(mut self) -> Result<SubscribeRequest, RequestError> {
+        self.validate()?;
+        self.request
+            .accounts_data_slice
+            .sort_by_key(|slice| slice.offset);
+        Ok(self.request)
+    }
+
+    fn validate(&self) -> Result<(), RequestError> {
+        let limits = &self.limits;
+        let request = &self.request;
+
+        check_filter_count("accounts", request.accounts.len(), limits)?;
+        check_filter_count("slots", request.slots.len(), limits)?;
+        check_filter_count("transactions", request.transactions.len(), limits)?;
+        check_filter_count(
+            "transactions_status",
+            request.transactions_status.len(),
+            limits,
+        )?;
+        check_filter_count("blocks", request.blocks.len(), limits)?;
+        check_filter_count("blocks_meta", request.blocks_meta.len(), limits)?;
+        check_filter_count("entry", request.entry.len(), limits)?;
+
+        for (name, filter) in &request.accounts {
+            validate_accounts(name, filter, limits)?;
+        }
+        for (name, filter) in request
+            .transactions
+            .iter()
+            .chain(request.transactions_status.iter())
+        {
+            validate_transactions(name, filter, limits)?;
+        }
+        for (name, filter) in &request.blocks {
+            check_pubkeys(name, "account_include", &filter.account_include, limits)?;
+        }
+
+        validate_data_slices(&request.accounts_data_slice, limits)
+    }
+}
+
+fn check_filter_count(
+    kind: &'static str,
+    count: usize,
+    limits: &FilterLimits,
+) -> Result<(), RequestError> {
+    if count > limits.max_filters {
+        return Err(RequestError::TooManyFilters {
+            kind,
+            count,
+            max: limits.max_filters,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_pubkeys(
+    filter: &str,
+    field: &'static str,
+    values: &[String],
+    limits: &FilterLimits,
+) -> Result<(), RequestError> {
+    if values.len() > limits.max_accounts {
+        return Err(RequestError::TooManyValues {
+            filter: filter.to_string(),
+            field,
+            count: values.len(),
+            max: limits.max_accounts,
+        });
+    }
+    for value in values {
+        if Pubkey::from_str(value).is_err() {
+            return Err(RequestError::InvalidPubkey {
+                filter: filter.to_string(),
+                value: value.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn validate_accounts(
+    name: &str,
+    filter: &SubscribeRequestFilterAccounts,
+    limits: &FilterLimits,
+) -> Result<(), RequestError> {
+    check_pubkeys(name, "account", &filter.account, limits)?;
+    check_pubkeys(name, "owner", &filter.owner, limits)?;
+
+    if filter.filters.len() > limits.max_account_conditions {
+        return Err(RequestError::TooManyValues {
+            filter: name.to_string(),
+            field: "filters",
+            count: filter.filters.len(),
+            max: limits.max_account_conditions,
+        });
+    }
+
+    let invalid = |reason: String| RequestError::InvalidMemcmp {
+        filter: name.to_string(),
+        reason,
+    };
+    for condition in &filter.filters {
+        let Some(AccountsFilterOneof::Memcmp(memcmp)) = &condition.filter else {
+            continue;
+        };
+        let len = match &memcmp.data {
+            Some(MemcmpData::Bytes(bytes)) => bytes.len(),
+            Some(MemcmpData::Base58(data)) => bs58::decode(data)
+                .into_vec()
+                .map_err(|e| invalid(format!("bad base58 `{}`: {}", data, e)))?
+                .len(),
+            Some(MemcmpData::Base64(data)) => general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| invalid(format!("bad base64 `{}`: {}", data, e)))?
+                .len(),
+            None => return Err(invalid("missing data".to_string())),
+        };
+        if len > MAX_MEMCMP_BYTES {
+            return Err(invalid(format!("{} bytes, max {}", len, MAX_MEMCMP_BYTES)));
+        }
+    }
+    Ok(())
+}
+
+fn validate_transactions(
+    name: &str,
+    filter: &SubscribeRequestFilterTransactions,
+    limits: &FilterLimits,
+) -> Result<(), RequestError> {
+    if let Some(signature) = &filter.signature
+        && Signature::from_str(signature).is_err()
+    {
+        return Err(RequestError::InvalidSignature {
+            filter: name.to_string(),
+            value: signature.clone(),
+        });
+    }
+    check_pubkeys(name, "account_include", &filter.account_include, limits)?;
+    check_pubkeys(name, "account_exclude", &filter.account_exclude, limits)?;
+    check_pubkeys(name, "account_required", &filter.account_required, limits)
+}
+
+// 服务端要求 data slice 互不重叠
+fn validate_data_slices(
+    slices: &[SubscribeRequestAccountsDataSlice],
+    limits: &FilterLimits,
+) -> Result<(), RequestError> {
+    if slices.len() > limits.max_data_slices {
+        return Err(RequestError::InvalidDataSlice(format!(
+            "{} slices, max {}",
+            slices.len(),
+            limits.max_data_slices
+        )));
+    }
+
+    // (offset, end)，offset + length 溢出 u64 的 slice 不合法
+    let mut ranges = slices
+        .iter()
+        .map(|slice| {
+            let end = slice.offset.checked_add(slice.length).ok_or_else(|| {
+                RequestError::InvalidDataSlice(format!(
+                    "offset {} + length {} overflows",
+                    slice.offset, slice.length
+                ))
+            })?;
+            Ok((slice.offset, end))
+        })
+        .collect::<Result<Vec<_>, RequestError>>()?;
+    ranges.sort_unstable();
+    for pair in ranges.windows(2) {
+        let ((offset, end), (next_offset, next_end)) = (pair[0], pair[1]);
+        if end > next_offset {
+            return Err(RequestError::InvalidDataSlice(format!(
+                "[{}, {}) overlaps [{}, {})",
+                offset, end, next_offset, next_end
+            )));
+        }
+    }
+    Ok(())
+}
+
+// 常用请求：订阅涉及指定程序（或钱包）的非投票、成功交易
+pub fn transactions_request(
+    filter: &str,
+    accounts: &[String],
+    commitment: CommitmentLevel,
+) -> Result<SubscribeRequest, RequestError> {
+    SubscribeRequestBuilder::new()
+        .transactions(
+            filter,
+            TransactionsFilter::new()
+                .vote(false)
+                .failed(false)
+                .include_all(accounts.iter().cloned()),
+        )
+        .commitment(commitment)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUMP: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+
+    #[test]
+    fn test_build_transactions() {
+        let request = SubscribeRequestBuilder::new()
+            .transactions(
+                "pump",
+                TransactionsFilter::new()
+                    .vote(false)
+                    .failed(false)
+                    .include(PUMP),
+            )
+            .commitment(CommitmentLevel::Processed)
+            .from_slot(100)
+            .build()
+            .unwrap();
+        assert_eq!(request.transactions["pump"].account_include, vec![PUMP]);
+        assert_eq!(request.commitment, Some(CommitmentLevel::Processed as i32));
+        assert_eq!(request.from_slot, Some(100));
+    }
+
+    #[test]
+    fn test_invalid_pubkey() {
+        let err = SubscribeRequestBuilder::new()
+            .accounts("curve", AccountsFilter::new().owner("not-a-key"))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RequestError::InvalidPubkey {
+                filter: "curve".to_string(),
+                value: "not-a-key".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_filter_limits() {
+        let limits = FilterLimits {
+            max_filters: 1,
+            ..Default::default()
+        };
+        let err = SubscribeRequestBuilder::new()
+            .limits(limits)
+            .blocks_meta("a")
+            .blocks_meta("b")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, RequestError::TooManyFilters { count: 2, .. }));
+    }
+
+    #[test]
+    fn test_overlapping_data_slices() {
+        let err = SubscribeRequestBuilder::new()
+            .data_slice(32, 16)
+            .data_slice(0, 40)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, RequestError::InvalidDataSlice(_)));
+    }
+
+    #[test]
+    fn test_data_slice_overflow() {
+        let err = SubscribeRequestBuilder::new()
+            .data_slice(0, 8)
+            .data_slice(u64::MAX, 1)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, RequestError::InvalidDataSlice(reason) if reason.contains("overflows"))
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use futures_util::future::BoxFuture;
+use log::debug;
+use yellowstone_grpc_proto::geyser::SubscribeUpdate;
+
+use crate::engine::{DispatchResult, UpdateDispatcher, into_result};
+
+// UpdateDispatcher 的方法返回 impl Future，不能直接做成 trait 对象，这里包一层
+trait BoxedDispatcher: Send {
+    fn on_update_boxed(&mut self, update: SubscribeUpdate) -> BoxFuture<'_, DispatchResult>;
+
+    fn flush_boxed(&mut self) -> BoxFuture<'_, DispatchResult>;
+}
+
+impl<D: UpdateDispatcher> BoxedDispatcher for D {
+    fn on_update_boxed(&mut self, update: SubscribeUpdate) -> BoxFuture<'_, DispatchResult> {
+        Box::pin(self.on_update(update))
+    }
+
+    fn flush_boxed(&mut self) -> BoxFuture<'_, DispatchResult> {
+        Box::pin(self.flush())
+    }
+}
+
+// 一个连接上订阅多个命名过滤器，按更新的 filters 分发给各自的处理器；
+// 同时匹配多个过滤器的更新会交给每个对应的处理器
+#[derive(Default)]
+pub struct FilterRouter {
+    routes: HashMap<String, usize>,
+    handlers: Vec<Box<dyn BoxedDispatcher>>,
+    // 没有匹配任何路由的更新
+    fallback: Option<Box<dyn BoxedDispatcher>>,
+    unrouted: u64,
+}
+
+impl FilterRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route<D>(self, filter: impl Into<String>, handler: D) -> Self
+    where
+        D: UpdateDispatcher + 'static,
+    {
+        self.route_filters([filter], handler)
+    }
+
+    // 多个过滤器共用同一个处理器
+    pub fn route_filters<I, S, D>(mut self, filters: I, handler: D) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+        D: UpdateDispatcher + 'static,
+    {
+        let index = self.handlers.len();
+        self.handlers.push(Box::new(handler));
+        for filter in filters {
+            self.routes.insert(filter.into(), index);
+        }
+        self
+    }
+
+    pub fn fallback<D>(mut self, handler: D) -> Self
+    where
+        D: UpdateDispatcher + 'static,
+    {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    pub fn filters(&self) -> impl Iterator<Item = &str> {
+        self.routes.keys().map(String::as_str)
+    }
+
+    pub fn unrouted(&self) -> u64 {
+        self.unrouted
+    }
+}
+
+impl UpdateDispatcher for FilterRouter {
+    async fn on_update(&mut self, update: SubscribeUpdate) -> DispatchResult {
+        let mut targets: Vec<usize> = update
+            .filters
+            .iter()
+            .filter_map(|filter| self.routes.get(filter).copied())
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+
+        let Some((&last, rest)) = targets.split_last() else {
+            return match self.fallback.as_mut() {
+                Some(fallback) => fallback.on_update_boxed(update).await,
+                None => {
+                    debug!("No route for filters {:?}", update.filters);
+                    self.unrouted += 1;
+                    Ok(())
+                }
+            };
+        };
+
+        // 一个处理器出错不影响其他处理器
+        let mut errors = vec![];
+        for &index in rest {
+            if let Err(e) = self.handlers[index].on_update_boxed(update.clone()).await {
+                errors.push(e.to_string());
+            }
+        }
+        if let Err(e) = self.handlers[last].on_update_boxed(update).await {
+            errors.push(e.to_string());
+        }
+        into_result(errors)
+    }
+
+    async fn flush(&mut self) -> DispatchResult {
+        let mut errors = vec![];
+        for handler in self.handlers.iter_mut().chain(self.fallback.as_mut()) {
+            if let Err(e) = handler.flush_boxed().await {
+                errors.push(e.to_string());
+            }
+        }
+        into_result(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use yellowstone_grpc_proto::geyser::{SubscribeUpdateSlot, subscribe_update::UpdateOneof};
+
+    use crate::engine::UpdateContext;
+
+    struct Recorder {
+        name: &'static str,
+        seen: Arc<Mutex<Vec<(&'static str, u64)>>>,
+    }
+
+    impl UpdateDispatcher for Recorder {
+        async fn on_slot(
+            &mut self,
+            _ctx: &UpdateContext,
+            slot: SubscribeUpdateSlot,
+        ) -> DispatchResult {
+            self.seen.lock().unwrap().push((self.name, slot.slot));
+            Ok(())
+        }
+    }
+
+    fn update(slot: u64, filters: &[&str]) -> SubscribeUpdate {
+        SubscribeUpdate {
+            filters: filters.iter().map(|f| f.to_string()).collect(),
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_by_filter_name() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let recorder = |name| Recorder {
+            name,
+            seen: seen.clone(),
+        };
+        let mut router = FilterRouter::new()
+            .route("pump", recorder("pump"))
+            .route_filters(["amm", "amm_pools"], recorder("amm"))
+            .route("wallets", recorder("wallets"));
+
+        router.on_update(update(1, &["pump"])).await.unwrap();
+        router
+            .on_update(update(2, &["amm", "amm_pools", "wallets"]))
+            .await
+            .unwrap();
+        router.on_update(update(3, &["unknown"])).await.unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![("pump", 1), ("amm", 2), ("wallets", 2)]
+        );
+        assert_eq!(router.unrouted(), 1);
+    }
+}
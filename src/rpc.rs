@@ -0,0 +1,320 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use tokio::{sync::watch, task::JoinHandle, time::MissedTickBehavior};
+use yellowstone_grpc_client::{GeyserGrpcClient, Interceptor};
+use yellowstone_grpc_proto::geyser::CommitmentLevel;
+
+use crate::{error::ClientError, grpc::YellowstoneGrpc};
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+// 每个 slot 最多增加一个区块，按 slot 时间估算两次刷新之间的区块高度（有跳过的 slot 时会偏大）
+const SLOT_DURATION: Duration = Duration::from_millis(400);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatestBlockhash {
+    pub blockhash: String,
+    pub slot: u64,
+    pub last_valid_block_height: u64,
+}
+
+impl LatestBlockhash {
+    // 超过 last_valid_block_height 后交易会被拒绝
+    pub fn is_expired(&self, block_height: u64) -> bool {
+        block_height > self.last_valid_block_height
+    }
+}
+
+// Geyser 的一元 RPC 封装，复用同一个连接
+pub struct GeyserRpc<F> {
+    client: GeyserGrpcClient<F>,
+}
+
+impl<F: Interceptor> GeyserRpc<F> {
+    pub fn new(client: GeyserGrpcClient<F>) -> Self {
+        Self { client }
+    }
+
+    pub async fn get_latest_blockhash(
+        &mut self,
+        commitment: CommitmentLevel,
+    ) -> Result<LatestBlockhash, ClientError> {
+        let response = self.client.get_latest_blockhash(Some(commitment)).await?;
+        Ok(LatestBlockhash {
+            blockhash: response.blockhash,
+            slot: response.slot,
+            last_valid_block_height: response.last_valid_block_height,
+        })
+    }
+
+    pub async fn get_block_height(
+        &mut self,
+        commitment: CommitmentLevel,
+    ) -> Result<u64, ClientError> {
+        let response = self.client.get_block_height(Some(commitment)).await?;
+        Ok(response.block_height)
+    }
+
+    pub async fn get_slot(&mut self, commitment: CommitmentLevel) -> Result<u64, ClientError> {
+        let response = self.client.get_slot(Some(commitment)).await?;
+        Ok(response.slot)
+    }
+
+    pub async fn is_blockhash_valid(
+        &mut self,
+        blockhash: &str,
+        commitment: CommitmentLevel,
+    ) -> Result<bool, ClientError> {
+        let response = self
+            .client
+            .is_blockhash_valid(blockhash.to_string(), Some(commitment))
+            .await?;
+        Ok(response.valid)
+    }
+
+    // 服务端返回的版本信息（JSON 字符串）
+    pub async fn get_version(&mut self) -> Result<String, ClientError> {
+        let response = self.client.get_version().await?;
+        Ok(response.version)
+    }
+
+    // 返回往返耗时
+    pub async fn ping(&mut self, count: i32) -> Result<Duration, ClientError> {
+        let started = Instant::now();
+        let response = self.client.ping(count).await?;
+        if response.count != count {
+            return Err(ClientError::Decode(format!(
+                "unexpected pong count {} (sent {})",
+                response.count, count
+            )));
+        }
+        Ok(started.elapsed())
+    }
+
+    // 服务端还能回放的最早 slot，None 表示不支持回放
+    pub async fn first_available_slot(&mut self) -> Result<Option<u64>, ClientError> {
+        let response = self.client.subscribe_replay_info().await?;
+        Ok(response.first_available)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockhashCacheConfig {
+    pub refresh_interval: Duration,
+    pub commitment: CommitmentLevel,
+}
+
+impl Default for BlockhashCacheConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            commitment: CommitmentLevel::Confirmed,
+        }
+    }
+}
+
+// 后台定时刷新最新 blockhash 和区块高度，每次刷新都用 IsBlockhashValid 校验，失效则清空；
+// 两次刷新之间按估算的区块高度判断是否过期
+pub struct BlockhashCache {
+    latest: watch::Receiver<Option<LatestBlockhash>>,
+    // 最近一次查到的区块高度和查询时间
+    block_height: Arc<Mutex<Option<(u64, Instant)>>>,
+    task: JoinHandle<()>,
+}
+
+impl BlockhashCache {
+    pub fn spawn(client: YellowstoneGrpc, config: BlockhashCacheConfig) -> Self {
+        let (tx, latest) = watch::channel(None);
+        let block_height = Arc::new(Mutex::new(None));
+        let height = block_height.clone();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.refresh_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut rpc = None;
+
+            loop {
+                interval.tick().await;
+                if rpc.is_none() {
+                    match client.rpc().await {
+                        Ok(connected) => rpc = Some(connected),
+                        Err(e) => {
+                            warn!("Blockhash cache failed to connect: {:?}", e);
+                            invalidate(&tx);
+                            continue;
+                        }
+                    }
+                }
+                let Some(connected) = rpc.as_mut() else {
+                    continue;
+                };
+
+                match connected.get_block_height(config.commitment).await {
+                    Ok(block_height) => {
+                        *height.lock().unwrap() = Some((block_height, Instant::now()));
+                    }
+                    Err(e) => warn!("Failed to get block height: {}", e),
+                }
+
+                match connected.get_latest_blockhash(config.commitment).await {
+                    Ok(blockhash) => {
+                        // 校验失败（查询出错）时仍然使用，由区块高度判断过期
+                        let valid = match connected
+                            .is_blockhash_valid(&blockhash.blockhash, config.commitment)
+                            .await
+                        {
+                            Ok(valid) => valid,
+                            Err(e) => {
+                                warn!("Failed to validate blockhash: {}", e);
+                                true
+                            }
+                        };
+                        if !valid {
+                            warn!("Latest blockhash {} is not valid", blockhash.blockhash);
+                            invalidate(&tx);
+                            continue;
+                        }
+                        debug!("Blockhash refreshed: {:?}", blockhash);
+                        tx.send_if_modified(|current| {
+                            let changed = current.as_ref() != Some(&blockhash);
+                            *current = Some(blockhash);
+                            changed
+                        });
+                    }
+                    Err(e) => {
+                        warn!("Failed to refresh blockhash: {}", e);
+                        let cached = tx.borrow().as_ref().map(|b| b.blockhash.clone());
+                        let valid = match cached {
+                            Some(blockhash) => connected
+                                .is_blockhash_valid(&blockhash, config.commitment)
+                                .await
+                                .unwrap_or(false),
+                            None => false,
+                        };
+                        if !valid {
+                            invalidate(&tx);
+                            // 下次重新建立连接
+                            rpc = None;
+                        }
+                    }
+                }
+            }
+        });
+        Self {
+            latest,
+            block_height,
+            task,
+        }
+    }
+
+    // 按估算的区块高度已经过期时返回 None
+    pub fn get(&self) -> Option<LatestBlockhash> {
+        let blockhash = self.latest.borrow().clone()?;
+        match self.block_height() {
+            Some(block_height) if blockhash.is_expired(block_height) => None,
+            _ => Some(blockhash),
+        }
+    }
+
+    // 上次查到的区块高度加上之后经过的 slot 数
+    pub fn block_height(&self) -> Option<u64> {
+        let (block_height, fetched_at) = (*self.block_height.lock().unwrap())?;
+        let elapsed = fetched_at.elapsed().as_millis() / SLOT_DURATION.as_millis();
+        Some(block_height + elapsed as u64)
+    }
+
+    // 等待第一次刷新成功
+    pub async fn wait(&self) -> Result<LatestBlockhash, ClientError> {
+        let mut latest = self.latest.clone();
+        let blockhash = latest
+            .wait_for(Option::is_some)
+            .await
+            .map_err(|_| ClientError::Disconnected("blockhash cache stopped".to_string()))?;
+        Ok(blockhash.clone().expect("checked by wait_for"))
+    }
+
+    pub fn watch(&self) -> watch::Receiver<Option<LatestBlockhash>> {
+        self.latest.clone()
+    }
+}
+
+impl Drop for BlockhashCache {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn invalidate(tx: &watch::Sender<Option<LatestBlockhash>>) {
+    tx.send_if_modified(|current| current.take().is_some());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockChain, MockGeyser};
+
+    #[test]
+    fn test_blockhash_expiry() {
+        let blockhash = LatestBlockhash {
+            blockhash: "11111111111111111111111111111111".to_string(),
+            slot: 100,
+            last_valid_block_height: 250,
+        };
+        assert!(!blockhash.is_expired(250));
+        assert!(blockhash.is_expired(251));
+    }
+
+    #[tokio::test]
+    async fn test_blockhash_cache_refresh_and_expiry() {
+        let server = MockGeyser::new()
+            .with_chain(MockChain {
+                // 拿到的 blockhash 已经过期
+                last_valid_block_height: Some(899),
+                ..Default::default()
+            })
+            .serve()
+            .await
+            .unwrap();
+        let cache = BlockhashCache::spawn(
+            server.client(),
+            BlockhashCacheConfig {
+                refresh_interval: Duration::from_millis(20),
+                ..Default::default()
+            },
+        );
+
+        let expired = cache.wait().await.unwrap();
+        assert_eq!(expired.last_valid_block_height, 899);
+        assert!(cache.block_height().unwrap() >= 900);
+        assert_eq!(cache.get(), None);
+
+        let blockhash = "4uQeVj5tqViQh7yWWGStvkEG1Zmhx6uasJtWCJziofM".to_string();
+        server.update_chain(|chain| {
+            chain.blockhash = blockhash.clone();
+            chain.last_valid_block_height = None;
+        });
+        let mut latest = cache.watch();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            latest.wait_for(|latest| {
+                latest
+                    .as_ref()
+                    .is_some_and(|latest| latest.blockhash == blockhash)
+            }),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(cache.get().unwrap().last_valid_block_height, 1050);
+
+        // 节点认为最新的 blockhash 无效时清空缓存
+        server.update_chain(|chain| chain.blockhash_valid = Some(false));
+        tokio::time::timeout(Duration::from_secs(5), latest.wait_for(Option::is_none))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cache.get(), None);
+    }
+}
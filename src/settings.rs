@@ -0,0 +1,691 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use solana_sdk::pubkey::Pubkey;
+use toml::{Value, value::Table};
+use yellowstone_grpc_proto::geyser::{CommitmentLevel, SubscribeRequest};
+
+use crate::{
+    config::{GrpcConfig, TlsOptions, read_token_file},
+    error::ClientError,
+    handle::{Decoder, EventHandler},
+    model::instruction::INSTRUCTION_TYPES,
+    output::{EventPrinter, OutputFormat, OutputWriter},
+    request::{AccountsFilter, SubscribeRequestBuilder, TransactionsFilter},
+};
+
+// 环境变量覆盖配置文件中的值：GRPC_JH__ENDPOINTS__MAIN__X_TOKEN 对应 endpoints.main.x_token，
+// 键名不区分大小写匹配配置文件中已有的键，新键转成小写
+pub const ENV_PREFIX: &str = "GRPC_JH__";
+
+pub type BoxedWriter = Box<dyn Write + Send>;
+
+#[derive(Clone, Debug)]
+pub struct SubscriptionSettings {
+    pub endpoint: String,
+    pub request: SubscribeRequest,
+}
+
+impl SubscriptionSettings {
+    pub fn filters(&self) -> impl Iterator<Item = &str> {
+        self.request
+            .transactions
+            .keys()
+            .chain(self.request.accounts.keys())
+            .map(String::as_str)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecoderSettings {
+    pub decoder: Decoder,
+    pub programs: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OutputSettings {
+    pub format: OutputFormat,
+    // 不设置时输出到 stdout
+    pub path: Option<PathBuf>,
+    // 为空表示全部事件类型 / 全部订阅
+    pub events: Vec<String>,
+    pub subscriptions: Vec<String>,
+}
+
+// 配置文件：端点、命名订阅、每个程序使用的解析器，以及事件输出到哪里
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub endpoints: BTreeMap<String, GrpcConfig>,
+    pub subscriptions: BTreeMap<String, SubscriptionSettings>,
+    // 没有 [decoders] 时为 None，运行全部解析器；全部 enabled = false 时为空，不解析事件
+    pub decoders: Option<Vec<DecoderSettings>>,
+    pub outputs: BTreeMap<String, OutputSettings>,
+}
+
+impl Settings {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        Self::load_with_env(path, env::vars())
+    }
+
+    pub fn load_with_env<I>(path: impl AsRef<Path>, env: I) -> Result<Self, ClientError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| {
+            ClientError::Config(format!("failed to read {}: {}", path.display(), e))
+        })?;
+        Self::from_toml(&text, env)
+            .map_err(|e| ClientError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn from_toml<I>(text: &str, env: I) -> Result<Self, ClientError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut root = text
+            .parse::<Value>()
+            .map_err(|e| ClientError::Config(e.to_string()))?;
+        let Value::Table(table) = &mut root else {
+            return Err(ClientError::Config("expected a table".to_string()));
+        };
+        apply_overrides(table, env)?;
+
+        let root = Section::root(table);
+        root.check_keys(&["endpoints", "subscriptions", "decoders", "outputs"])?;
+
+        let mut endpoints = BTreeMap::new();
+        for (name, section) in root.required_tables("endpoints")? {
+            endpoints.insert(name, parse_endpoint(&section)?);
+        }
+
+        let mut subscriptions = BTreeMap::new();
+        // 所有订阅的更新交给同一组输出，过滤器名称不能重复
+        let mut filter_owners: HashMap<String, String> = HashMap::new();
+        for (name, section) in root.required_tables("subscriptions")? {
+            let subscription = parse_subscription(&section, &endpoints)?;
+            for filter in subscription.filters() {
+                if let Some(owner) = filter_owners.insert(filter.to_string(), name.clone()) {
+                    return Err(section.error(
+                        filter,
+                        &format!("filter name already used by subscription {}", owner),
+                    ));
+                }
+            }
+            subscriptions.insert(name, subscription);
+        }
+
+        let mut decoders = root.table.contains_key("decoders").then(Vec::new);
+        for (name, section) in root.tables("decoders")? {
+            section.check_keys(&["programs", "enabled"])?;
+            let decoder = Decoder::from_str(&name).map_err(|_| {
+                root.error(
+                    &format!("decoders.{}", name),
+                    &format!(
+                        "unknown decoder (expected one of {})",
+                        Decoder::ALL.map(Decoder::name).join(", ")
+                    ),
+                )
+            })?;
+            if !section.bool("enabled")?.unwrap_or(true) {
+                continue;
+            }
+            let mut programs = section.pubkeys("programs")?;
+            if programs.is_empty() {
+                programs.push(decoder.program_id().to_string());
+            }
+            if let Some(decoders) = decoders.as_mut() {
+                decoders.push(DecoderSettings { decoder, programs });
+            }
+        }
+
+        let mut outputs = BTreeMap::new();
+        for (name, section) in root.tables("outputs")? {
+            outputs.insert(name, parse_output(&section, &subscriptions)?);
+        }
+        if outputs.is_empty() {
+            outputs.insert("stdout".to_string(), OutputSettings::default());
+        }
+
+        Ok(Self {
+            endpoints,
+            subscriptions,
+            decoders,
+            outputs,
+        })
+    }
+
+    // 没有配置 decoders 时运行全部解析器
+    pub fn event_handler(&self) -> EventHandler {
+        match &self.decoders {
+            Some(decoders) => EventHandler::new().with_decoders(
+                decoders
+                    .iter()
+                    .map(|settings| (settings.decoder, settings.programs.clone())),
+            ),
+            None => EventHandler::new(),
+        }
+    }
+
+    pub fn printers(&self) -> Result<Vec<EventPrinter<BoxedWriter>>, ClientError> {
+        let mut printers = vec![];
+        for (name, output) in &self.outputs {
+            let writer: BoxedWriter = match &output.path {
+                Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|e| {
+                    ClientError::Config(format!(
+                        "outputs.{}.path: failed to create {}: {}",
+                        name,
+                        path.display(),
+                        e
+                    ))
+                })?)),
+                None => Box::new(io::stdout()),
+            };
+            let filters = output
+                .subscriptions
+                .iter()
+                .flat_map(|subscription| self.subscriptions[subscription].filters());
+            printers.push(
+                EventPrinter::new(OutputWriter::new(output.format, writer))
+                    .with_decoder(self.event_handler())
+                    .with_events(output.events.iter().cloned())
+                    .with_filters(filters),
+            );
+        }
+        Ok(printers)
+    }
+}
+
+fn parse_endpoint(section: &Section) -> Result<GrpcConfig, ClientError> {
+    section.check_keys(&[
+        "url",
+        "x_token",
+        "x_token_file",
+        "plaintext",
+        "ca_cert",
+        "client_cert",
+        "client_key",
+        "domain_name",
+    ])?;
+    let endpoint = section
+        .str("url")?
+        .ok_or_else(|| section.error("url", "missing"))?;
+    let x_token = match (section.str("x_token")?, section.str("x_token_file")?) {
+        (Some(_), Some(_)) => {
+            return Err(section.error("x_token_file", "cannot be used together with x_token"));
+        }
+        (Some(token), None) => Some(token),
+        (None, Some(path)) => Some(
+            read_token_file(&path).map_err(|e| section.error("x_token_file", &e.to_string()))?,
+        ),
+        (None, None) => None,
+    };
+
+    let tls = TlsOptions {
+        plaintext: section.bool("plaintext")?.unwrap_or_default(),
+        ca_cert: section.str("ca_cert")?.map(PathBuf::from),
+        client_cert: section.str("client_cert")?.map(PathBuf::from),
+        client_key: section.str("client_key")?.map(PathBuf::from),
+        domain_name: section.str("domain_name")?,
+    };
+    if tls.client_cert.is_some() != tls.client_key.is_some() {
+        return Err(section.error(
+            "client_key",
+            "client_cert and client_key must be set together",
+        ));
+    }
+    Ok(GrpcConfig {
+        endpoint,
+        x_token,
+        tls,
+    })
+}
+
+fn parse_subscription(
+    section: &Section,
+    endpoints: &BTreeMap<String, GrpcConfig>,
+) -> Result<SubscriptionSettings, ClientError> {
+    section.check_keys(&[
+        "endpoint",
+        "commitment",
+        "from_slot",
+        "transactions",
+        "accounts",
+    ])?;
+    let endpoint = match section.str("endpoint")? {
+        Some(endpoint) if endpoints.contains_key(&endpoint) => endpoint,
+        Some(endpoint) => {
+            return Err(section.error("endpoint", &format!("unknown endpoint {}", endpoint)));
+        }
+        None if endpoints.len() == 1 => endpoints.keys().next().cloned().unwrap_or_default(),
+        None => {
+            return Err(section.error("endpoint", "required when several endpoints are configured"));
+        }
+    };
+
+    let mut builder =
+        SubscribeRequestBuilder::new().commitment(match section.str("commitment")?.as_deref() {
+            None | Some("processed") => CommitmentLevel::Processed,
+            Some("confirmed") => CommitmentLevel::Confirmed,
+            Some("finalized") => CommitmentLevel::Finalized,
+            Some(other) => {
+                return Err(section.error(
+                    "commitment",
+                    &format!(
+                        "unknown commitment {} (expected processed, confirmed or finalized)",
+                        other
+                    ),
+                ));
+            }
+        });
+    if let Some(slot) = section.u64("from_slot")? {
+        builder = builder.from_slot(slot);
+    }
+
+    let transactions = section.tables("transactions")?;
+    let accounts = section.tables("accounts")?;
+    if transactions.is_empty() && accounts.is_empty() {
+        return Err(section.error("transactions", "no transactions or accounts filters"));
+    }
+    for (name, filter) in transactions {
+        filter.check_keys(&["include", "exclude", "required", "vote", "failed"])?;
+        let mut transactions = TransactionsFilter::new()
+            .vote(filter.bool("vote")?.unwrap_or(false))
+            .failed(filter.bool("failed")?.unwrap_or(false))
+            .include_all(filter.pubkeys("include")?);
+        for pubkey in filter.pubkeys("exclude")? {
+            transactions = transactions.exclude(pubkey);
+        }
+        for pubkey in filter.pubkeys("required")? {
+            transactions = transactions.required(pubkey);
+        }
+        builder = builder.transactions(name, transactions);
+    }
+    for (name, filter) in accounts {
+        filter.check_keys(&["accounts", "owners"])?;
+        let mut accounts = AccountsFilter::new().accounts(filter.pubkeys("accounts")?);
+        for owner in filter.pubkeys("owners")? {
+            accounts = accounts.owner(owner);
+        }
+        builder = builder.accounts(name, accounts);
+    }
+
+    let request = builder
+        .build()
+        .map_err(|e| ClientError::Config(format!("{}: {}", section.path, e)))?;
+    Ok(SubscriptionSettings { endpoint, request })
+}
+
+fn parse_output(
+    section: &Section,
+    subscriptions: &BTreeMap<String, SubscriptionSettings>,
+) -> Result<OutputSettings, ClientError> {
+    section.check_keys(&["format", "path", "events", "subscriptions"])?;
+    let format = match section.str("format")? {
+        Some(format) => format
+            .parse()
+            .map_err(|e: ClientError| section.error("format", &e.to_string()))?,
+        None => OutputFormat::default(),
+    };
+
+    let events = section.strings("events")?;
+    let known: Vec<&str> = Decoder::ALL
+        .iter()
+        .flat_map(|decoder| decoder.event_types())
+        .copied()
+        .chain(INSTRUCTION_TYPES)
+        .chain(["transaction", "account"])
+        .collect();
+    if let Some(unknown) = events.iter().find(|event| !known.contains(&event.as_str())) {
+        return Err(section.error(
+            "events",
+            &format!(
+                "unknown event type {} (expected one of {})",
+                unknown,
+                known.join(", ")
+            ),
+        ));
+    }
+
+    let names = section.strings("subscriptions")?;
+    if let Some(unknown) = names.iter().find(|name| !subscriptions.contains_key(*name)) {
+        return Err(section.error(
+            "subscriptions",
+            &format!("unknown subscription {}", unknown),
+        ));
+    }
+
+    Ok(OutputSettings {
+        format,
+        path: section.str("path")?.map(PathBuf::from),
+        events,
+        subscriptions: names,
+    })
+}
+
+fn apply_overrides<I>(root: &mut Table, env: I) -> Result<(), ClientError>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    for (key, raw) in env {
+        let Some(path) = key.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let path: Vec<&str> = path.split("__").collect();
+        let Some((leaf, parents)) = path.split_last() else {
+            continue;
+        };
+
+        let mut table = &mut *root;
+        for parent in parents {
+            let value = table
+                .entry(existing_key(table, parent))
+                .or_insert_with(|| Value::Table(Table::new()));
+            let Value::Table(next) = value else {
+                return Err(ClientError::Config(format!(
+                    "{}: {} is not a table",
+                    key,
+                    path.join(".")
+                )));
+            };
+            table = next;
+        }
+        table.insert(existing_key(table, leaf), env_value(&raw));
+    }
+    Ok(())
+}
+
+// 环境变量名通常是大写，按不区分大小写匹配已有的键
+fn existing_key(table: &Table, key: &str) -> String {
+    table
+        .keys()
+        .find(|existing| existing.eq_ignore_ascii_case(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_lowercase())
+}
+
+// 能按 TOML 解析的值（数字、布尔、数组）按原类型覆盖，其余当作字符串
+fn env_value(raw: &str) -> Value {
+    format!("value = {}", raw)
+        .parse::<Value>()
+        .ok()
+        .and_then(|value| value.get("value").cloned())
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+// 带完整键路径的表，错误信息指向出错的键
+struct Section<'a> {
+    path: String,
+    table: &'a Table,
+}
+
+impl<'a> Section<'a> {
+    fn root(table: &'a Table) -> Self {
+        Self {
+            path: String::new(),
+            table,
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", self.path, key)
+        }
+    }
+
+    fn error(&self, key: &str, message: &str) -> ClientError {
+        ClientError::Config(format!("{}: {}", self.key(key), message))
+    }
+
+    fn check_keys(&self, allowed: &[&str]) -> Result<(), ClientError> {
+        match self
+            .table
+            .keys()
+            .find(|key| !allowed.contains(&key.as_str()))
+        {
+            Some(key) => Err(self.error(key, "unknown key")),
+            None => Ok(()),
+        }
+    }
+
+    fn str(&self, key: &str) -> Result<Option<String>, ClientError> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(self.error(key, "expected a string")),
+        }
+    }
+
+    fn bool(&self, key: &str) -> Result<Option<bool>, ClientError> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(Value::Boolean(value)) => Ok(Some(*value)),
+            Some(_) => Err(self.error(key, "expected true or false")),
+        }
+    }
+
+    fn u64(&self, key: &str) -> Result<Option<u64>, ClientError> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(Value::Integer(value)) if *value >= 0 => Ok(Some(*value as u64)),
+            Some(_) => Err(self.error(key, "expected a non-negative integer")),
+        }
+    }
+
+    // 环境变量里的列表可以写成逗号分隔的字符串
+    fn strings(&self, key: &str) -> Result<Vec<String>, ClientError> {
+        match self.table.get(key) {
+            None => Ok(vec![]),
+            Some(Value::String(value)) => Ok(value
+                .split(',')
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect()),
+            Some(Value::Array(values)) => values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| match value {
+                    Value::String(value) => Ok(value.clone()),
+                    _ => Err(self.error(&format!("{}[{}]", key, i), "expected a string")),
+                })
+                .collect(),
+            Some(_) => Err(self.error(key, "expected a list of strings")),
+        }
+    }
+
+    fn pubkeys(&self, key: &str) -> Result<Vec<String>, ClientError> {
+        let values = self.strings(key)?;
+        for (i, value) in values.iter().enumerate() {
+            if Pubkey::from_str(value).is_err() {
+                return Err(self.error(
+                    &format!("{}[{}]", key, i),
+                    &format!("invalid pubkey {}", value),
+                ));
+            }
+        }
+        Ok(values)
+    }
+
+    // 形如 [key.<name>] 的命名子表
+    fn tables(&self, key: &str) -> Result<Vec<(String, Section<'a>)>, ClientError> {
+        let table = match self.table.get(key) {
+            None => return Ok(vec![]),
+            Some(Value::Table(table)) => table,
+            Some(_) => return Err(self.error(key, "expected a table")),
+        };
+        table
+            .iter()
+            .map(|(name, value)| {
+                let path = self.key(&format!("{}.{}", key, name));
+                match value {
+                    Value::Table(table) => Ok((name.clone(), Section { path, table })),
+                    _ => Err(ClientError::Config(format!("{}: expected a table", path))),
+                }
+            })
+            .collect()
+    }
+
+    fn required_tables(&self, key: &str) -> Result<Vec<(String, Section<'a>)>, ClientError> {
+        let tables = self.tables(key)?;
+        if tables.is_empty() {
+            return Err(self.error(key, "at least one entry is required"));
+        }
+        Ok(tables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EventTrait, pumpfun_model::CreateEvent};
+
+    const CONFIG: &str = r#"
+        [endpoints.main]
+        url = "https://solana-yellowstone-grpc.publicnode.com"
+
+        [subscriptions.pump]
+        commitment = "confirmed"
+        transactions.pump = { include = ["6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"] }
+        transactions.pump_amm = { include = ["pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA"] }
+
+        [decoders.pump]
+
+        [outputs.trades]
+        format = "json"
+        events = ["pump_trade", "amm_buy", "amm_sell"]
+        subscriptions = ["pump"]
+    "#;
+
+    fn load(text: &str, env: &[(&str, &str)]) -> Result<Settings, ClientError> {
+        Settings::from_toml(
+            text,
+            env.iter().map(|(k, v)| (k.to_string(), v.to_string())),
+        )
+    }
+
+    #[test]
+    fn test_load_settings() {
+        let settings = load(CONFIG, &[]).unwrap();
+        let subscription = &settings.subscriptions["pump"];
+        assert_eq!(subscription.endpoint, "main");
+        assert_eq!(
+            subscription.request.commitment,
+            Some(CommitmentLevel::Confirmed as i32)
+        );
+        assert_eq!(subscription.request.transactions.len(), 2);
+        assert_eq!(
+            settings.decoders,
+            Some(vec![DecoderSettings {
+                decoder: Decoder::Pump,
+                programs: vec![Decoder::Pump.program_id().to_string()],
+            }])
+        );
+        assert_eq!(settings.outputs["trades"].format, OutputFormat::Json);
+
+        let example = Settings::load_with_env(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/config.example.toml"),
+            [],
+        )
+        .unwrap();
+        assert_eq!(example.subscriptions.len(), 2);
+        assert_eq!(example.outputs["trades"].format, OutputFormat::Csv);
+    }
+
+    #[test]
+    fn test_all_decoders_disabled() {
+        let config = CONFIG.replace(
+            "[decoders.pump]",
+            "[decoders.pump]\nenabled = false\n[decoders.pump_amm]\nenabled = false",
+        );
+        let settings = load(&config, &[]).unwrap();
+        assert_eq!(settings.decoders, Some(vec![]));
+
+        let logs = vec![
+            format!("Program {} invoke [1]", Decoder::Pump.program_id()),
+            format!(
+                "Program data: {}",
+                base64::Engine::encode(
+                    &base64::engine::general_purpose::STANDARD,
+                    CreateEvent::DISCRIMINATOR
+                        .into_iter()
+                        .chain(borsh::to_vec(&CreateEvent::default()).unwrap())
+                        .collect::<Vec<u8>>()
+                )
+            ),
+        ];
+        assert!(settings.event_handler().decode_logs(&logs).is_empty());
+        assert_eq!(
+            load(CONFIG, &[])
+                .unwrap()
+                .event_handler()
+                .decode_logs(&logs)
+                .len(),
+            1
+        );
+
+        let unconfigured = load(&CONFIG.replace("[decoders.pump]", ""), &[]).unwrap();
+        assert_eq!(unconfigured.decoders, None);
+    }
+
+    #[test]
+    fn test_errors_point_at_key() {
+        let error = |text: &str| load(text, &[]).unwrap_err().to_string();
+        assert!(
+            error(&CONFIG.replace("\"confirmed\"", "\"fast\""))
+                .contains("subscriptions.pump.commitment")
+        );
+        assert!(
+            error(&CONFIG.replace("\"amm_buy\"", "\"amm_swap\"")).contains("outputs.trades.events")
+        );
+        assert!(
+            error(&CONFIG.replace("pAMMBay6", "not-a-key"))
+                .contains("subscriptions.pump.transactions.pump_amm.include[0]")
+        );
+        assert!(
+            error(&CONFIG.replace("[decoders.pump]", "[decoders.raydium]"))
+                .contains("decoders.raydium")
+        );
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let settings = load(
+            CONFIG,
+            &[
+                ("GRPC_JH__ENDPOINTS__MAIN__X_TOKEN", "secret"),
+                ("GRPC_JH__SUBSCRIPTIONS__PUMP__FROM_SLOT", "42"),
+                ("GRPC_JH__OUTPUTS__TRADES__EVENTS", "pump_create,pump_trade"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            settings.endpoints["main"].x_token.as_deref(),
+            Some("secret")
+        );
+        assert_eq!(settings.subscriptions["pump"].request.from_slot, Some(42));
+        assert_eq!(
+            settings.outputs["trades"].events,
+            ["pump_create", "pump_trade"]
+        );
+
+        // 配置文件中带大写字母的名称也能覆盖
+        let settings = load(
+            &CONFIG.replace("[outputs.trades]", "[outputs.Trades]"),
+            &[("GRPC_JH__OUTPUTS__TRADES__FORMAT", "csv")],
+        )
+        .unwrap();
+        assert_eq!(settings.outputs["Trades"].format, OutputFormat::Csv);
+        assert!(!settings.outputs.contains_key("trades"));
+
+        let error = load(CONFIG, &[("GRPC_JH__ENDPOINTS__MAIN__PLAINTEXT", "yes")]).unwrap_err();
+        assert!(error.to_string().contains("endpoints.main.plaintext"));
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    future::Future,
+    io,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+use tokio::task::JoinSet;
+pub use tokio_util::sync::CancellationToken;
+
+use crate::pipeline::QueueMetrics;
+
+const DEFAULT_DEADLINE: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownSummary {
+    // 在期限内正常退出的任务
+    pub finished: usize,
+    // 超过期限被强制中止的任务
+    pub aborted: usize,
+    pub processed: u64,
+    pub dropped: u64,
+    // 退出时仍在队列中、没有处理的更新
+    pub pending: u64,
+    pub elapsed: Duration,
+}
+
+// 统一管理订阅任务的退出：取消 token 后订阅循环停止接收新数据，
+// 队列中已有的数据处理完并 flush，超过期限仍未退出的任务会被中止
+pub struct Shutdown {
+    token: CancellationToken,
+    deadline: Duration,
+    tasks: JoinSet<()>,
+    queues: Vec<Arc<QueueMetrics>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            deadline: DEFAULT_DEADLINE,
+            tasks: JoinSet::new(),
+            queues: vec![],
+        }
+    }
+
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    // 传给 YellowstoneGrpc::with_shutdown
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    // 退出时汇总这个队列的处理数量
+    pub fn track_queue(&mut self, metrics: Arc<QueueMetrics>) {
+        self.queues.push(metrics);
+    }
+
+    pub fn spawn<F>(&mut self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task);
+    }
+
+    // 等待 Ctrl-C，或者 token 在别处被取消
+    pub async fn wait_for_signal(&self) -> io::Result<()> {
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = self.token.cancelled() => Ok(()),
+        }
+    }
+
+    pub async fn shutdown(mut self) -> ShutdownSummary {
+        info!("Shutting down, waiting up to {:?}", self.deadline);
+        let started = Instant::now();
+        self.token.cancel();
+
+        let mut summary = ShutdownSummary::default();
+        let deadline = tokio::time::sleep(self.deadline);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                joined = self.tasks.join_next() => match joined {
+                    Some(Ok(())) => summary.finished += 1,
+                    Some(Err(e)) => {
+                        error!("Task failed during shutdown: {:?}", e);
+                        summary.finished += 1;
+                    }
+                    None => break,
+                },
+                _ = &mut deadline => {
+                    summary.aborted = self.tasks.len();
+                    warn!("Shutdown deadline exceeded, aborting {} tasks", summary.aborted);
+                    self.tasks.shutdown().await;
+                    break;
+                }
+            }
+        }
+
+        for queue in &self.queues {
+            summary.processed += queue.processed.load(Ordering::Relaxed);
+            summary.dropped += queue.dropped.load(Ordering::Relaxed);
+            summary.pending += queue.depth.load(Ordering::Relaxed);
+        }
+        summary.elapsed = started.elapsed();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_abort_after_deadline() {
+        let mut shutdown = Shutdown::new().with_deadline(Duration::from_millis(50));
+        let token = shutdown.token();
+        shutdown.spawn(async move { token.cancelled().await });
+        // 不响应取消的任务
+        shutdown.spawn(std::future::pending());
+
+        let summary = shutdown.shutdown().await;
+        assert_eq!(summary.finished, 1);
+        assert_eq!(summary.aborted, 1);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use yellowstone_grpc_proto::geyser::{
+    SlotStatus, SubscribeRequest, SubscribeRequestFilterSlots, SubscribeUpdateSlot,
+};
+
+// 缺口检测使用的 slot 过滤器名称，该过滤器的更新不会交给业务回调
+pub const GAP_DETECTOR_FILTER: &str = "slot_gaps";
+// 保留多少个 slot 的 parent / dead 信息
+const HISTORY_SLOTS: u64 = 4096;
+
+#[derive(Clone, Debug)]
+pub struct GapDetectionConfig {
+    // 按哪个状态检查连续性
+    pub status: SlotStatus,
+    // 发现丢失的 slot 时断开并用 from_slot 重新订阅
+    pub backfill: bool,
+    // 超过这个长度的缺口不回填
+    pub max_backfill_slots: u64,
+}
+
+impl Default for GapDetectionConfig {
+    fn default() -> Self {
+        Self {
+            status: SlotStatus::SlotConfirmed,
+            backfill: false,
+            max_backfill_slots: 150,
+        }
+    }
+}
+
+impl GapDetectionConfig {
+    // 在原有过滤条件上增加 slot 订阅，接收所有状态的更新
+    pub fn apply(&self, request: &mut SubscribeRequest) {
+        request.slots.insert(
+            GAP_DETECTOR_FILTER.to_string(),
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(false),
+                interslot_updates: Some(false),
+            },
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GapKind {
+    // 不在当前分叉的 parent 链上，leader 没有出块
+    Skipped,
+    // 收到过 SlotDead
+    Dead,
+    // 在 parent 链上但没有收到，数据被服务端丢掉了
+    Dropped,
+    // 缺少 parent 信息，无法判断
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotGap {
+    pub kind: GapKind,
+    pub from_slot: u64,
+    pub to_slot: u64,
+}
+
+impl SlotGap {
+    pub fn slot_count(&self) -> u64 {
+        self.to_slot - self.from_slot + 1
+    }
+}
+
+// 根据 slot 状态更新检查连续性：用 parent 链区分 leader 跳过的 slot 和丢失的 slot
+#[derive(Debug)]
+pub struct SlotGapDetector {
+    status: SlotStatus,
+    last_slot: Option<u64>,
+    parents: BTreeMap<u64, u64>,
+    dead: BTreeSet<u64>,
+}
+
+impl SlotGapDetector {
+    pub fn new(status: SlotStatus) -> Self {
+        Self {
+            status,
+            last_slot: None,
+            parents: BTreeMap::new(),
+            dead: BTreeSet::new(),
+        }
+    }
+
+    pub fn last_slot(&self) -> Option<u64> {
+        self.last_slot
+    }
+
+    pub fn observe(&mut self, update: &SubscribeUpdateSlot) -> Vec<SlotGap> {
+        if let Some(parent) = update.parent {
+            self.parents.insert(update.slot, parent);
+        }
+        if update.status == SlotStatus::SlotDead as i32 {
+            self.dead.insert(update.slot);
+        }
+        if update.status != self.status as i32 {
+            return vec![];
+        }
+
+        let Some(last) = self.last_slot else {
+            self.last_slot = Some(update.slot);
+            return vec![];
+        };
+        // 回放或分叉切换导致的旧 slot
+        if update.slot <= last {
+            return vec![];
+        }
+        self.last_slot = Some(update.slot);
+
+        let gaps = self.classify(last, update.slot);
+        self.prune(update.slot);
+        gaps
+    }
+
+    // 把 (last, slot) 之间的每个 slot 分类后合并成连续区间
+    fn classify(&self, last: u64, slot: u64) -> Vec<SlotGap> {
+        // 沿 parent 链往回走，链上的 slot 都应该出现过
+        let mut chain = BTreeSet::new();
+        let mut cursor = slot;
+        let mut resolved = false;
+        while let Some(&parent) = self.parents.get(&cursor) {
+            if parent <= last {
+                resolved = true;
+                break;
+            }
+            chain.insert(parent);
+            cursor = parent;
+        }
+
+        let mut gaps: Vec<SlotGap> = vec![];
+        for missing in last + 1..slot {
+            let kind = if self.dead.contains(&missing) {
+                GapKind::Dead
+            } else if chain.contains(&missing) {
+                GapKind::Dropped
+            } else if resolved || missing > cursor {
+                GapKind::Skipped
+            } else {
+                GapKind::Unknown
+            };
+            match gaps.last_mut() {
+                Some(gap) if gap.kind == kind && gap.to_slot + 1 == missing => {
+                    gap.to_slot = missing
+                }
+                _ => gaps.push(SlotGap {
+                    kind,
+                    from_slot: missing,
+                    to_slot: missing,
+                }),
+            }
+        }
+        gaps
+    }
+
+    fn prune(&mut self, slot: u64) {
+        let keep_from = slot.saturating_sub(HISTORY_SLOTS);
+        self.parents = self.parents.split_off(&keep_from);
+        self.dead = self.dead.split_off(&keep_from);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(slot: u64, parent: Option<u64>, status: SlotStatus) -> SubscribeUpdateSlot {
+        SubscribeUpdateSlot {
+            slot,
+            parent,
+            status: status as i32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_skipped_and_dropped_slots() {
+        let mut detector = SlotGapDetector::new(SlotStatus::SlotConfirmed);
+        assert!(
+            detector
+                .observe(&update(100, None, SlotStatus::SlotConfirmed))
+                .is_empty()
+        );
+
+        // 101 被 leader 跳过，102 的 parent 是 100
+        detector.observe(&update(102, Some(100), SlotStatus::SlotProcessed));
+        assert_eq!(
+            detector.observe(&update(102, None, SlotStatus::SlotConfirmed)),
+            vec![SlotGap {
+                kind: GapKind::Skipped,
+                from_slot: 101,
+                to_slot: 101
+            }]
+        );
+
+        // 103 在 parent 链上却没有收到确认
+        detector.observe(&update(103, Some(102), SlotStatus::SlotProcessed));
+        detector.observe(&update(104, Some(103), SlotStatus::SlotProcessed));
+        assert_eq!(
+            detector.observe(&update(104, None, SlotStatus::SlotConfirmed)),
+            vec![SlotGap {
+                kind: GapKind::Dropped,
+                from_slot: 103,
+                to_slot: 103
+            }]
+        );
+    }
+
+    #[test]
+    fn test_dead_and_unknown_slots() {
+        let mut detector = SlotGapDetector::new(SlotStatus::SlotConfirmed);
+        detector.observe(&update(10, None, SlotStatus::SlotConfirmed));
+        detector.observe(&update(11, None, SlotStatus::SlotDead));
+        assert_eq!(
+            detector.observe(&update(14, None, SlotStatus::SlotConfirmed)),
+            vec![
+                SlotGap {
+                    kind: GapKind::Dead,
+                    from_slot: 11,
+                    to_slot: 11
+                },
+                SlotGap {
+                    kind: GapKind::Unknown,
+                    from_slot: 12,
+                    to_slot: 13
+                },
+            ]
+        );
+    }
+}
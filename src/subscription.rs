@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use yellowstone_grpc_proto::geyser::{
+    SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions,
+};
+
+use crate::{
+    error::ClientError,
+    request::{FilterLimits, RequestError, check_pubkeys},
+};
+
+pub const PROGRAMS_FILTER: &str = "programs";
+pub const WALLETS_FILTER: &str = "wallets";
+
+// 运行中的订阅控制句柄：修改过滤条件后会直接发送到当前连接，重连时也使用最新的请求
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    request: Arc<watch::Sender<SubscribeRequest>>,
+    // add_* 添加账户时使用的检查，与 SubscribeRequestBuilder 一致
+    limits: FilterLimits,
+}
+
+impl SubscriptionHandle {
+    pub fn new(request: SubscribeRequest) -> Self {
+        let (request, _) = watch::channel(request);
+        Self {
+            request: Arc::new(request),
+            limits: FilterLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: FilterLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn current_request(&self) -> SubscribeRequest {
+        self.request.borrow().clone()
+    }
+
+    pub fn watch(&self) -> watch::Receiver<SubscribeRequest> {
+        self.request.subscribe()
+    }
+
+    // 只有请求实际发生变化时才通知订阅循环
+    pub fn update<F>(&self, f: F) -> bool
+    where
+        F: FnOnce(&mut SubscribeRequest),
+    {
+        self.request.send_if_modified(|request| {
+            let before = request.clone();
+            f(request);
+            *request != before
+        })
+    }
+
+    pub fn replace(&self, request: SubscribeRequest) -> bool {
+        self.update(|current| *current = request)
+    }
+
+    // f 返回错误时恢复原来的请求，不通知订阅循环
+    fn try_update<F>(&self, f: F) -> Result<bool, ClientError>
+    where
+        F: FnOnce(&mut SubscribeRequest) -> Result<(), RequestError>,
+    {
+        let mut result = Ok(());
+        let modified = self.request.send_if_modified(|request| {
+            let before = request.clone();
+            result = f(request);
+            if result.is_err() {
+                *request = before;
+                return false;
+            }
+            *request != before
+        });
+        result?;
+        Ok(modified)
+    }
+
+    pub fn add_program(&self, program_id: &str) -> Result<bool, ClientError> {
+        self.add_transaction_account(PROGRAMS_FILTER, program_id)
+    }
+
+    pub fn remove_program(&self, program_id: &str) -> bool {
+        self.remove_transaction_account(PROGRAMS_FILTER, program_id)
+    }
+
+    pub fn add_wallet(&self, wallet: &str) -> Result<bool, ClientError> {
+        self.add_transaction_account(WALLETS_FILTER, wallet)
+    }
+
+    pub fn remove_wallet(&self, wallet: &str) -> bool {
+        self.remove_transaction_account(WALLETS_FILTER, wallet)
+    }
+
+    // account 不是合法的公钥或超过数量限制时返回错误，请求保持不变
+    pub fn add_transaction_account(
+        &self,
+        filter: &str,
+        account: &str,
+    ) -> Result<bool, ClientError> {
+        self.try_update(|request| {
+            let tx_filter = request
+                .transactions
+                .entry(filter.to_string())
+                .or_insert_with(|| SubscribeRequestFilterTransactions {
+                    vote: Some(false),
+                    failed: Some(false),
+                    ..Default::default()
+                });
+            if !tx_filter.account_include.iter().any(|a| a == account) {
+                tx_filter.account_include.push(account.to_string());
+            }
+            check_pubkeys(
+                filter,
+                "account_include",
+                &tx_filter.account_include,
+                &self.limits,
+            )
+        })
+    }
+
+    // account_include 为空时会匹配所有交易，所以删掉最后一个账户时整个过滤器一起删除
+    pub fn remove_transaction_account(&self, filter: &str, account: &str) -> bool {
+        self.update(|request| {
+            if let Some(tx_filter) = request.transactions.get_mut(filter) {
+                tx_filter.account_include.retain(|a| a != account);
+                if tx_filter.account_include.is_empty() {
+                    request.transactions.remove(filter);
+                }
+            }
+        })
+    }
+
+    pub fn set_account_filter(&self, name: &str, filter: SubscribeRequestFilterAccounts) -> bool {
+        self.update(|request| {
+            request.accounts.insert(name.to_string(), filter);
+        })
+    }
+
+    pub fn remove_account_filter(&self, name: &str) -> bool {
+        self.update(|request| {
+            request.accounts.remove(name);
+        })
+    }
+
+    // 例如新建的 Pump 代币，监听其 bonding curve 账户
+    pub fn add_account(&self, filter: &str, account: &str) -> Result<bool, ClientError> {
+        self.try_update(|request| {
+            let account_filter = request.accounts.entry(filter.to_string()).or_default();
+            if !account_filter.account.iter().any(|a| a == account) {
+                account_filter.account.push(account.to_string());
+            }
+            check_pubkeys(filter, "account", &account_filter.account, &self.limits)
+        })
+    }
+
+    pub fn remove_account(&self, filter: &str, account: &str) -> bool {
+        self.update(|request| {
+            if let Some(account_filter) = request.accounts.get_mut(filter) {
+                account_filter.account.retain(|a| a != account);
+                if account_filter.account.is_empty()
+                    && account_filter.owner.is_empty()
+                    && account_filter.filters.is_empty()
+                {
+                    request.accounts.remove(filter);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUMP: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+    const PUMP_AMM: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
+
+    #[test]
+    fn test_add_remove_program() {
+        let handle = SubscriptionHandle::new(SubscribeRequest::default());
+        let mut rx = handle.watch();
+
+        assert!(handle.add_program(PUMP).unwrap());
+        assert!(handle.add_program(PUMP_AMM).unwrap());
+        assert!(!handle.add_program(PUMP).unwrap());
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(
+            handle.current_request().transactions[PROGRAMS_FILTER].account_include,
+            vec![PUMP, PUMP_AMM]
+        );
+
+        rx.mark_unchanged();
+        assert!(handle.remove_program(PUMP));
+        assert!(handle.remove_program(PUMP_AMM));
+        assert!(rx.has_changed().unwrap());
+        assert!(handle.current_request().transactions.is_empty());
+    }
+
+    #[test]
+    fn test_add_rejects_invalid_pubkey() {
+        let handle = SubscriptionHandle::new(SubscribeRequest::default());
+        let rx = handle.watch();
+
+        assert!(matches!(
+            handle.add_wallet("not-a-key"),
+            Err(ClientError::Request(RequestError::InvalidPubkey { .. }))
+        ));
+        assert!(handle.add_account("curve", "not-a-key").is_err());
+        assert!(!rx.has_changed().unwrap());
+        assert_eq!(handle.current_request(), SubscribeRequest::default());
+
+        let handle = handle.with_limits(FilterLimits {
+            max_accounts: 1,
+            ..Default::default()
+        });
+        assert!(handle.add_program(PUMP).unwrap());
+        assert!(handle.add_program(PUMP_AMM).is_err());
+        assert_eq!(
+            handle.current_request().transactions[PROGRAMS_FILTER].account_include,
+            vec![PUMP]
+        );
+    }
+}
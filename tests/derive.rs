@@ -0,0 +1,7 @@
+// 在其他 crate 中使用 #[derive(SolanaEvent)]，同一 crate 中 discriminator 重复时编译失败
+#[test]
+fn test_derive_in_external_crate() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/external_event.rs");
+    cases.compile_fail("tests/ui/duplicate_discriminator.rs");
+}
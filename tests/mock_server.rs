@@ -0,0 +1,426 @@
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use grpc_jh::{
+    checkpoint::{CheckpointConfig, Checkpointer},
+    error::ClientError,
+    grpc::YellowstoneGrpc,
+    handle::EventHandler,
+    latency::LatencyStage,
+    mock::{self, MockChain, MockGeyser, MockStep},
+    model::{EventTrait, pumpfun_model::CreateEvent},
+    reconnect::{ReconnectPolicy, WatchdogConfig},
+    request::transactions_request,
+    shutdown::Shutdown,
+    slots::{GAP_DETECTOR_FILTER, GapDetectionConfig},
+    subscription::SubscriptionHandle,
+};
+use tokio::sync::mpsc;
+use yellowstone_grpc_proto::{
+    geyser::{
+        CommitmentLevel, SlotStatus, SubscribeRequest, SubscribeUpdate,
+        subscribe_update::UpdateOneof,
+    },
+    prost_types::Timestamp,
+    tonic::Status,
+};
+
+const PUMP: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn fast_reconnect() -> ReconnectPolicy {
+    ReconnectPolicy {
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+        jitter: 0.0,
+        ..Default::default()
+    }
+}
+
+fn request() -> SubscribeRequest {
+    transactions_request("client", &[PUMP.to_string()], CommitmentLevel::Processed).unwrap()
+}
+
+// 在后台订阅，把收到的更新转发到 channel
+fn spawn_subscription(client: YellowstoneGrpc) -> mpsc::UnboundedReceiver<SubscribeUpdate> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let _ = client
+            .subscribe_supervised(request(), move |update| {
+                let tx = tx.clone();
+                async move {
+                    tx.send(update)
+                        .map_err(|e| ClientError::Handler(e.to_string()))?;
+                    Ok(())
+                }
+            })
+            .await;
+    });
+    rx
+}
+
+fn transaction(slot: u64, signature: u8) -> SubscribeUpdate {
+    mock::transaction_update(slot, &[signature; 64], vec![])
+}
+
+async fn next_slot(rx: &mut mpsc::UnboundedReceiver<SubscribeUpdate>) -> u64 {
+    let update = tokio::time::timeout(TIMEOUT, rx.recv())
+        .await
+        .expect("timed out waiting for update")
+        .expect("subscription stopped");
+    match update.update_oneof {
+        Some(UpdateOneof::Slot(slot)) => slot.slot,
+        Some(UpdateOneof::Transaction(tx)) => tx.slot,
+        other => panic!("unexpected update: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_reconnect_resumes_from_last_slot() {
+    let server = MockGeyser::new()
+        .with_script(vec![
+            MockStep::Update(transaction(10, 1)),
+            MockStep::Update(transaction(11, 2)),
+            // slot 状态更新不推进续订位置
+            MockStep::Update(mock::slot_update(13)),
+            MockStep::Disconnect,
+        ])
+        .with_script(vec![MockStep::Error(Status::unavailable(
+            "node restarting",
+        ))])
+        // 从 slot 11 回放，已经处理过的交易不再交给回调
+        .with_script(vec![
+            MockStep::Update(transaction(11, 2)),
+            MockStep::Update(transaction(12, 3)),
+        ])
+        .serve()
+        .await
+        .unwrap();
+    let client = server.client().with_reconnect_policy(fast_reconnect());
+    let metrics = client.metrics();
+
+    let mut rx = spawn_subscription(client);
+    assert_eq!(next_slot(&mut rx).await, 10);
+    assert_eq!(next_slot(&mut rx).await, 11);
+    assert_eq!(next_slot(&mut rx).await, 13);
+    assert_eq!(next_slot(&mut rx).await, 12);
+
+    assert_eq!(server.connections(), 3);
+    assert_eq!(metrics.reconnects.load(Ordering::Relaxed), 2);
+    let resumed: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|request| request.ping.is_none())
+        .map(|request| request.from_slot)
+        .collect();
+    // 第一次从最新位置订阅，之后都从断开前最后处理的 slot 继续
+    assert_eq!(resumed.first(), Some(&None));
+    assert_eq!(resumed.last(), Some(&Some(11)));
+}
+
+#[tokio::test]
+async fn test_auth_error_stops_reconnecting() {
+    let server = MockGeyser::new()
+        .with_script(vec![MockStep::Error(Status::unauthenticated(
+            "invalid x-token",
+        ))])
+        .with_script(vec![MockStep::Update(mock::slot_update(12))])
+        .serve()
+        .await
+        .unwrap();
+    let client = server.client().with_reconnect_policy(fast_reconnect());
+
+    let result = tokio::time::timeout(
+        TIMEOUT,
+        client.subscribe_supervised(request(), |_| async { Ok(()) }),
+    )
+    .await
+    .expect("subscription should stop on a fatal error");
+    assert!(matches!(result, Err(ClientError::Auth(_))));
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn test_shutdown_drains_queue() {
+    let server = MockGeyser::new()
+        .with_script(vec![
+            MockStep::Update(mock::slot_update(40)),
+            MockStep::Update(mock::slot_update(41)),
+            MockStep::Sleep(Duration::from_secs(60)),
+        ])
+        .serve()
+        .await
+        .unwrap();
+    let mut shutdown = Shutdown::new().with_deadline(TIMEOUT);
+    let client = server.client().with_shutdown(shutdown.token());
+    shutdown.track_queue(client.queue_metrics());
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    shutdown.spawn(async move {
+        let result = client
+            .subscribe_pipelined(SubscriptionHandle::new(request()), move |update| {
+                let tx = tx.clone();
+                async move {
+                    // 处理较慢，退出时队列中还有数据
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    tx.send(update)
+                        .map_err(|e| ClientError::Handler(e.to_string()))?;
+                    Ok(())
+                }
+            })
+            .await;
+        assert!(result.is_ok());
+    });
+    while server.requests().is_empty() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let summary = shutdown.shutdown().await;
+    assert_eq!(summary.finished, 1);
+    assert_eq!(summary.aborted, 0);
+    assert_eq!(summary.processed, 2);
+    assert_eq!(summary.pending, 0);
+    assert_eq!(next_slot(&mut rx).await, 40);
+    assert_eq!(next_slot(&mut rx).await, 41);
+}
+
+#[tokio::test]
+async fn test_resume_from_checkpoint() {
+    let path = std::env::temp_dir().join(format!(
+        "grpc_jh_resume_checkpoint_{}.json",
+        std::process::id()
+    ));
+    let config = CheckpointConfig::new(&path, "pump");
+    let previous = Checkpointer::new(config.clone());
+    previous.record(50);
+    previous.flush().unwrap();
+
+    // 服务端只能回放到 60，检查点之后的 50..59 已经丢失
+    let server = MockGeyser::new()
+        .with_chain(MockChain {
+            first_available: Some(60),
+            ..Default::default()
+        })
+        .with_script(vec![MockStep::Update(transaction(61, 1))])
+        .serve()
+        .await
+        .unwrap();
+    let client = server.client().with_checkpoint(config);
+    let checkpoint = client.checkpoint().unwrap().clone();
+    let metrics = client.metrics();
+
+    let mut rx = spawn_subscription(client);
+    assert_eq!(next_slot(&mut rx).await, 61);
+
+    let first_request = server
+        .requests()
+        .into_iter()
+        .find(|request| request.ping.is_none())
+        .unwrap();
+    assert_eq!(first_request.from_slot, Some(60));
+    assert_eq!(metrics.gaps.load(Ordering::Relaxed), 1);
+    // 回调返回后才记录检查点
+    tokio::time::timeout(TIMEOUT, async {
+        while checkpoint.slot() != Some(61) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    checkpoint.flush().unwrap();
+    assert_eq!(previous.load().unwrap(), Some(61));
+    std::fs::remove_file(&path).unwrap();
+}
+
+fn slot_status(slot: u64, parent: Option<u64>, status: SlotStatus) -> MockStep {
+    let mut update = mock::slot_update(slot);
+    update.filters = vec![GAP_DETECTOR_FILTER.to_string()];
+    if let Some(UpdateOneof::Slot(slot)) = update.update_oneof.as_mut() {
+        slot.parent = parent;
+        slot.status = status as i32;
+    }
+    MockStep::Update(update)
+}
+
+#[tokio::test]
+async fn test_dropped_slot_triggers_backfill() {
+    let server = MockGeyser::new()
+        .with_script(vec![
+            slot_status(100, None, SlotStatus::SlotConfirmed),
+            slot_status(101, Some(100), SlotStatus::SlotProcessed),
+            slot_status(102, Some(101), SlotStatus::SlotProcessed),
+            slot_status(102, None, SlotStatus::SlotConfirmed),
+        ])
+        .with_script(vec![MockStep::Update(mock::slot_update(101))])
+        .serve()
+        .await
+        .unwrap();
+    let client = server.client().with_gap_detection(GapDetectionConfig {
+        backfill: true,
+        ..Default::default()
+    });
+    let metrics = client.metrics();
+
+    // 缺口检测自己的 slot 更新不会交给业务回调
+    let mut rx = spawn_subscription(client);
+    assert_eq!(next_slot(&mut rx).await, 101);
+
+    assert_eq!(metrics.dropped_slots.load(Ordering::Relaxed), 1);
+    assert_eq!(metrics.backfills.load(Ordering::Relaxed), 1);
+    let last_request = server
+        .requests()
+        .into_iter()
+        .rfind(|request| request.ping.is_none())
+        .unwrap();
+    assert_eq!(last_request.from_slot, Some(101));
+    assert!(last_request.slots.contains_key(GAP_DETECTOR_FILTER));
+}
+
+#[tokio::test]
+async fn test_ping_handling() {
+    let server = MockGeyser::new()
+        .with_script(vec![
+            MockStep::Update(mock::ping_update()),
+            MockStep::Sleep(Duration::from_millis(200)),
+            MockStep::Update(mock::slot_update(20)),
+        ])
+        .serve()
+        .await
+        .unwrap();
+    let client = server.client().with_watchdog(WatchdogConfig {
+        ping_interval: Duration::from_millis(50),
+        stale_timeout: Duration::from_secs(5),
+    });
+    let metrics = client.metrics();
+
+    // 服务端 ping 不会交给业务回调
+    let mut rx = spawn_subscription(client);
+    assert_eq!(next_slot(&mut rx).await, 20);
+
+    assert!(
+        server
+            .requests()
+            .iter()
+            .any(|request| request.ping.is_some())
+    );
+    assert!(metrics.pongs_received.load(Ordering::Relaxed) > 0);
+    assert!(metrics.last_rtt().is_some());
+}
+
+#[tokio::test]
+async fn test_decode_pump_create_event() {
+    let event = CreateEvent {
+        name: "Mock".to_string(),
+        symbol: "MOCK".to_string(),
+        uri: "https://example.com/mock.json".to_string(),
+        timestamp: 1_700_000_000,
+        ..Default::default()
+    };
+    let logs = vec![
+        format!("Program {} invoke [1]", PUMP),
+        mock::program_data_log(
+            CreateEvent::discriminator(),
+            &borsh::to_vec(&event).unwrap(),
+        ),
+        format!("Program {} success", PUMP),
+    ];
+    let signature = [7u8; 64];
+    let server = MockGeyser::new()
+        .with_script(vec![MockStep::Update(mock::transaction_update(
+            30, &signature, logs,
+        ))])
+        .serve()
+        .await
+        .unwrap();
+
+    let mut rx = spawn_subscription(server.client());
+    let update = tokio::time::timeout(TIMEOUT, rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let Some(UpdateOneof::Transaction(sut)) = update.update_oneof else {
+        panic!("expected a transaction update");
+    };
+
+    let mut handler = EventHandler::new();
+    handler.handle_transaction(sut).await.unwrap();
+    let events = handler.events(&solana_sdk::bs58::encode(signature).into_string());
+    assert_eq!(events.len(), 1);
+    let (slot, tx_event) = &events[0];
+    assert_eq!(*slot, 30);
+    assert_eq!(tx_event.log_index, 1);
+    assert_eq!(tx_event.depth, 1);
+    assert_eq!(tx_event.event_type, CreateEvent::NAME);
+    assert_eq!(tx_event.event, format!("{:?}", event));
+}
+
+#[tokio::test]
+async fn test_latency_per_filter_and_event() {
+    let logs = vec![
+        format!("Program {} invoke [1]", PUMP),
+        mock::program_data_log(
+            CreateEvent::discriminator(),
+            &borsh::to_vec(&CreateEvent::default()).unwrap(),
+        ),
+        format!("Program {} success", PUMP),
+    ];
+    let mut update = mock::transaction_update(30, &[9u8; 64], logs);
+    update.filters = vec!["client".to_string()];
+    // 服务端 200ms 前生成的更新
+    let created_at =
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap() - Duration::from_millis(200);
+    update.created_at = Some(Timestamp {
+        seconds: created_at.as_secs() as i64,
+        nanos: created_at.subsec_nanos() as i32,
+    });
+    let server = MockGeyser::new()
+        .with_script(vec![MockStep::Update(update)])
+        .serve()
+        .await
+        .unwrap();
+    let client = server.client();
+    let latency = client.latency();
+
+    tokio::spawn(async move { client.subscribe(PUMP.to_string()).await });
+    let decode = tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Some(histogram) = latency.histogram(LatencyStage::Decode, "pump_create") {
+                return histogram;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(decode.count(), 1);
+    assert!(decode.max() >= Duration::from_millis(200));
+
+    let receive = latency.histogram(LatencyStage::Receive, "client").unwrap();
+    assert_eq!(receive.count(), 1);
+    assert!(receive.max() >= Duration::from_millis(200));
+    assert!(receive.max() <= decode.max());
+}
+
+#[tokio::test]
+async fn test_unary_rpcs() {
+    let server = MockGeyser::new().serve().await.unwrap();
+    let mut rpc = server.client().rpc().await.unwrap();
+
+    assert_eq!(
+        rpc.get_slot(CommitmentLevel::Processed).await.unwrap(),
+        1_000
+    );
+    let latest = rpc
+        .get_latest_blockhash(CommitmentLevel::Confirmed)
+        .await
+        .unwrap();
+    assert!(
+        rpc.is_blockhash_valid(&latest.blockhash, CommitmentLevel::Confirmed)
+            .await
+            .unwrap()
+    );
+    assert_eq!(rpc.first_available_slot().await.unwrap(), Some(0));
+}
@@ -0,0 +1,18 @@
+use borsh::BorshDeserialize;
+use grpc_jh::model::SolanaEvent;
+
+grpc_jh::solana_events!();
+
+#[derive(Debug, BorshDeserialize, SolanaEvent)]
+#[event(discriminator = [1, 2, 3, 4, 5, 6, 7, 8])]
+struct First {
+    amount: u64,
+}
+
+#[derive(Debug, BorshDeserialize, SolanaEvent)]
+#[event(discriminator = [1, 2, 3, 4, 5, 6, 7, 8])]
+struct Second {
+    amount: u64,
+}
+
+fn main() {}
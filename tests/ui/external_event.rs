@@ -0,0 +1,18 @@
+use borsh::BorshDeserialize;
+use grpc_jh::{decode::registered_events, model::SolanaEvent};
+
+grpc_jh::solana_events!();
+
+#[derive(Debug, BorshDeserialize, SolanaEvent)]
+#[event(name = "external_swap")]
+struct ExternalSwap {
+    amount: u64,
+}
+
+fn main() {
+    assert!(
+        registered_events()
+            .iter()
+            .any(|event| event.event_type == "external_swap")
+    );
+}